@@ -0,0 +1,400 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Signature, H160, H256},
+};
+use futures_util::future::{self, BoxFuture};
+use tracing::{error, info};
+use zeroize::Zeroizing;
+
+use crate::{
+    helpers::now_timestamp_ms, prelude::*, signature::sign_hash, ActionSigner, Error,
+    ExchangeClient, ExchangeResponseStatus, KeystorePassword,
+};
+
+/// Suffix of keystores written for an agent that hasn't been approved yet.
+const PENDING_SUFFIX: &str = ".pending";
+
+fn io_error(path: &Path, e: impl std::fmt::Display) -> Error {
+    Error::Io(format!("{}: {e}", path.display()))
+}
+
+/// An agent wallet approved to trade on behalf of a master account.
+#[derive(Debug, Clone)]
+pub struct Agent {
+    pub name: String,
+    pub wallet: LocalWallet,
+    /// When the agent was created, in milliseconds since the epoch.
+    pub created_at: u64,
+}
+
+/// Stores agent keys as encrypted JSON keystores in a directory, readable
+/// only by the owner.
+///
+/// Every key of an agent is written to `{name}-{created_at}.json`, so the
+/// newest file holds the current key of the agent. A key is written with a
+/// `.pending` suffix until the exchange approved it.
+pub struct AgentStore {
+    directory: PathBuf,
+    password: Zeroizing<String>,
+}
+
+impl AgentStore {
+    /// Opens the store in `directory`, creating it if needed. The passphrase
+    /// is read once and used for all keystores.
+    pub fn new(directory: PathBuf, password: &KeystorePassword) -> Result<Self> {
+        fs::create_dir_all(&directory).map_err(|e| io_error(&directory, e))?;
+        restrict_permissions(&directory, 0o700)?;
+        let password = password.read(&directory)?;
+        Ok(Self {
+            directory,
+            password,
+        })
+    }
+
+    /// Loads the newest approved key of the agent called `name`.
+    pub fn load(&self, name: &str) -> Result<Option<Agent>> {
+        let Some((created_at, path)) = self.keystores(name)?.pop() else {
+            return Ok(None);
+        };
+        let wallet = LocalWallet::decrypt_keystore(&path, self.password.as_str())
+            .map_err(|e| Error::Wallet(format!("{}: {e}", path.display())))?;
+        Ok(Some(Agent {
+            name: name.to_string(),
+            wallet,
+            created_at,
+        }))
+    }
+
+    /// Generates a new key for the agent called `name` and writes it as
+    /// pending. It's only loaded after `commit` is called for it.
+    pub fn create(&self, name: &str) -> Result<Agent> {
+        if !is_valid_agent_name(name) {
+            return Err(Error::Wallet(format!(
+                "invalid agent name {name}: use letters, digits, '_' and '-'"
+            )));
+        }
+        let created_at = now_timestamp_ms();
+        let file_name = format!("{name}-{created_at}.json{PENDING_SUFFIX}");
+        let (wallet, _) = LocalWallet::new_keystore(
+            &self.directory,
+            &mut rand::thread_rng(),
+            self.password.as_str(),
+            Some(&file_name),
+        )
+        .map_err(|e| Error::Wallet(e.to_string()))?;
+        restrict_permissions(&self.directory.join(file_name), 0o600)?;
+        Ok(Agent {
+            name: name.to_string(),
+            wallet,
+            created_at,
+        })
+    }
+
+    /// Marks the pending key of `agent` as approved.
+    pub fn commit(&self, agent: &Agent) -> Result<()> {
+        let path = self.path(agent);
+        let pending = pending_path(&path);
+        fs::rename(&pending, &path).map_err(|e| io_error(&pending, e))
+    }
+
+    /// Deletes the pending key of `agent`.
+    pub fn discard(&self, agent: &Agent) -> Result<()> {
+        let pending = pending_path(&self.path(agent));
+        fs::remove_file(&pending).map_err(|e| io_error(&pending, e))
+    }
+
+    /// Deletes the keys of the agent called `name` that were replaced by a
+    /// newer one.
+    pub fn prune(&self, name: &str) -> Result<()> {
+        let mut keystores = self.keystores(name)?;
+        keystores.pop();
+        for (_, path) in keystores {
+            fs::remove_file(&path).map_err(|e| io_error(&path, e))?;
+        }
+        Ok(())
+    }
+
+    fn path(&self, agent: &Agent) -> PathBuf {
+        self.directory
+            .join(format!("{}-{}.json", agent.name, agent.created_at))
+    }
+
+    /// The approved keystores of the agent called `name`, oldest first.
+    fn keystores(&self, name: &str) -> Result<Vec<(u64, PathBuf)>> {
+        let entries = fs::read_dir(&self.directory).map_err(|e| io_error(&self.directory, e))?;
+        let prefix = format!("{name}-");
+        let mut keystores = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| io_error(&self.directory, e))?.path();
+            let created_at = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| file_name.strip_prefix(&prefix))
+                .and_then(|file_name| file_name.strip_suffix(".json"))
+                .and_then(|created_at| created_at.parse().ok());
+            if let Some(created_at) = created_at {
+                keystores.push((created_at, path));
+            }
+        }
+        keystores.sort();
+        Ok(keystores)
+    }
+}
+
+/// Whether `name` can be used as an agent name and in a file name.
+pub(crate) fn is_valid_agent_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn pending_path(path: &Path) -> PathBuf {
+    let mut pending = path.as_os_str().to_owned();
+    pending.push(PENDING_SUFFIX);
+    pending.into()
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).map_err(|e| io_error(path, e))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+/// Signs with the current key of an agent. The key is replaced in place
+/// when the agent is rotated, so exchange clients built with it keep
+/// working across rotations.
+pub struct AgentSigner {
+    wallet: RwLock<LocalWallet>,
+}
+
+impl AgentSigner {
+    pub fn new(wallet: LocalWallet) -> Self {
+        Self {
+            wallet: RwLock::new(wallet),
+        }
+    }
+
+    /// Signs with `wallet` from now on.
+    pub fn replace(&self, wallet: LocalWallet) {
+        *self.wallet.write().unwrap_or_else(PoisonError::into_inner) = wallet;
+    }
+
+    fn wallet(&self) -> LocalWallet {
+        self.wallet
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl ActionSigner for AgentSigner {
+    fn address(&self) -> H160 {
+        Signer::address(&self.wallet())
+    }
+
+    fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<Signature>> {
+        Box::pin(future::ready(Ok(sign_hash(hash, &self.wallet()))))
+    }
+}
+
+/// Creates, persists and rotates named agent wallets of the master account
+/// that signs for `exchange_client`.
+///
+/// Orders signed by an agent are placed for the master account, so a
+/// trading process only needs the agent key, while account queries keep
+/// using the master address.
+pub struct AgentManager {
+    exchange_client: ExchangeClient,
+    store: AgentStore,
+    rotation_interval: Option<Duration>,
+}
+
+impl AgentManager {
+    /// `rotation_interval` is the age after which an agent is replaced by a
+    /// new one, or `None` to keep agents until they are rotated explicitly.
+    pub fn new(
+        exchange_client: ExchangeClient,
+        store: AgentStore,
+        rotation_interval: Option<Duration>,
+    ) -> Self {
+        Self {
+            exchange_client,
+            store,
+            rotation_interval,
+        }
+    }
+
+    /// The current agent called `name`, creating and approving it if it
+    /// doesn't exist yet or is due for rotation.
+    pub async fn agent(&self, name: &str) -> Result<Agent> {
+        match self.store.load(name)? {
+            Some(agent) if !self.is_due(&agent) => Ok(agent),
+            _ => self.rotate(name).await,
+        }
+    }
+
+    /// Creates a new key for the agent called `name` and approves it,
+    /// replacing the previous key of the agent on the exchange.
+    pub async fn rotate(&self, name: &str) -> Result<Agent> {
+        let agent = self.store.create(name)?;
+        let approval = self
+            .exchange_client
+            .approve_named_agent(Signer::address(&agent.wallet), Some(name), None)
+            .await;
+        let error = match approval {
+            Ok(ExchangeResponseStatus::Ok(_)) => None,
+            Ok(ExchangeResponseStatus::Err(e)) => Some(Error::Wallet(format!(
+                "approving agent {name} was rejected: {e}"
+            ))),
+            Err(e) => Some(e),
+        };
+        if let Some(e) = error {
+            self.store.discard(&agent)?;
+            return Err(e);
+        }
+        self.store.commit(&agent)?;
+        self.store.prune(name)?;
+        info!(
+            "Approved agent {name} with address {:?}",
+            Signer::address(&agent.wallet)
+        );
+        Ok(agent)
+    }
+
+    /// Whether `agent` is older than the rotation interval.
+    pub fn is_due(&self, agent: &Agent) -> bool {
+        self.rotation_interval.is_some_and(|interval| {
+            let interval_ms = interval.as_millis().try_into().unwrap_or(u64::MAX);
+            now_timestamp_ms().saturating_sub(agent.created_at) >= interval_ms
+        })
+    }
+
+    /// Rotates the agent called `name` whenever it is due, handing the new
+    /// key to `signer`. Returns immediately if rotation is disabled.
+    pub async fn run(self, name: String, signer: Arc<AgentSigner>) {
+        let Some(interval) = self.rotation_interval else {
+            return;
+        };
+        // Retry failed rotations well before the next one is due
+        let retry = interval.min(Duration::from_secs(60));
+        loop {
+            let delay = match self.store.load(&name) {
+                Ok(Some(agent)) => {
+                    let age =
+                        Duration::from_millis(now_timestamp_ms().saturating_sub(agent.created_at));
+                    interval.saturating_sub(age)
+                }
+                Ok(None) => Duration::ZERO,
+                Err(e) => {
+                    error!("Failed to load agent {name}: {e}");
+                    retry
+                }
+            };
+            tokio::time::sleep(delay).await;
+
+            match self.agent(&name).await {
+                Ok(agent) => signer.replace(agent.wallet),
+                Err(e) => {
+                    error!("Failed to rotate agent {name}: {e}");
+                    tokio::time::sleep(retry).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> Result<AgentStore> {
+        let directory = std::env::temp_dir().join(format!("agents-{}", uuid::Uuid::new_v4()));
+        std::env::set_var("AGENTS_TEST_PASSWORD", "passphrase");
+        AgentStore::new(
+            directory,
+            &KeystorePassword::Env("AGENTS_TEST_PASSWORD".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_only_committed_keys_are_loaded() -> Result<()> {
+        let store = store()?;
+        assert!(store.load("mm")?.is_none());
+
+        let first = store.create("mm")?;
+        assert!(store.load("mm")?.is_none());
+        store.commit(&first)?;
+        let loaded = store.load("mm")?.expect("committed agent");
+        assert_eq!(
+            Signer::address(&loaded.wallet),
+            Signer::address(&first.wallet)
+        );
+        assert_eq!(loaded.created_at, first.created_at);
+
+        std::thread::sleep(Duration::from_millis(2));
+        let second = store.create("mm")?;
+        store.discard(&second)?;
+        let loaded = store.load("mm")?.expect("committed agent");
+        assert_eq!(
+            Signer::address(&loaded.wallet),
+            Signer::address(&first.wallet)
+        );
+        assert!(store.load("other")?.is_none());
+
+        fs::remove_dir_all(&store.directory).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_keeps_newest_key() -> Result<()> {
+        let store = store()?;
+        let first = store.create("mm")?;
+        store.commit(&first)?;
+        std::thread::sleep(Duration::from_millis(2));
+        let second = store.create("mm")?;
+        store.commit(&second)?;
+
+        store.prune("mm")?;
+        assert_eq!(store.keystores("mm")?.len(), 1);
+        let loaded = store.load("mm")?.expect("committed agent");
+        assert_eq!(
+            Signer::address(&loaded.wallet),
+            Signer::address(&second.wallet)
+        );
+
+        fs::remove_dir_all(&store.directory).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_names_are_rejected() -> Result<()> {
+        let store = store()?;
+        assert!(store.create("../mm").is_err());
+        assert!(store.create("").is_err());
+        fs::remove_dir_all(&store.directory).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_agent_signer_uses_replaced_key() {
+        let first = LocalWallet::new(&mut rand::thread_rng());
+        let second = LocalWallet::new(&mut rand::thread_rng());
+        let signer = AgentSigner::new(first.clone());
+        assert_eq!(ActionSigner::address(&signer), Signer::address(&first));
+        signer.replace(second.clone());
+        assert_eq!(ActionSigner::address(&signer), Signer::address(&second));
+    }
+}
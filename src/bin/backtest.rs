@@ -0,0 +1,95 @@
+#![warn(clippy::all, clippy::nursery, clippy::pedantic)]
+
+use std::path::PathBuf;
+
+use ethers::signers::LocalWallet;
+use hyperliquid_rust_sdk::{
+    read_recording, BacktestConfig, Backtester, MarketMakerConfig, SimulationConfig,
+};
+use tracing::info;
+
+const USAGE: &str = "Usage: backtest --config <market_maker.toml> [--asset ETH] --sz-decimals 4 \
+<recording.jsonl.gz>";
+
+struct Args {
+    config: PathBuf,
+    asset: Option<String>,
+    sz_decimals: u32,
+    recording: PathBuf,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut config = None;
+        let mut asset = None;
+        let mut sz_decimals = None;
+        let mut recording = None;
+
+        let mut raw = std::env::args().skip(1);
+        while let Some(arg) = raw.next() {
+            if !arg.starts_with("--") {
+                if recording.replace(PathBuf::from(arg)).is_some() {
+                    return Err("Only one recording can be replayed".to_string());
+                }
+                continue;
+            }
+            let value = raw
+                .next()
+                .ok_or_else(|| format!("Missing value for {arg}"))?;
+            match arg.as_str() {
+                "--config" => config = Some(value.into()),
+                "--asset" => asset = Some(value),
+                "--sz-decimals" => {
+                    sz_decimals = Some(value.parse().map_err(|e| format!("{arg}: {e}"))?);
+                }
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+        Ok(Self {
+            config: config.ok_or("Missing --config")?,
+            asset,
+            sz_decimals: sz_decimals.ok_or("Missing --sz-decimals")?,
+            recording: recording.ok_or("Missing recording")?,
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let args = Args::parse().unwrap_or_else(|e| panic!("{e}\n{USAGE}"));
+    let config = MarketMakerConfig::load(&args.config).expect("Failed to load config");
+    let market = match &args.asset {
+        Some(asset) => config.markets.iter().find(|market| &market.asset == asset),
+        None if config.markets.len() == 1 => config.markets.first(),
+        None => panic!("The config has several markets, pick one with --asset\n{USAGE}"),
+    }
+    .unwrap_or_else(|| panic!("No market for {:?} in the config", args.asset));
+    let messages = read_recording(&args.recording).expect("Failed to read recording");
+
+    // The wallet only identifies the simulated account
+    let input = market.input(LocalWallet::new(&mut rand::thread_rng()));
+    // Simulated like paper trading, if the config sets it up
+    let simulation = config.paper.clone().unwrap_or_else(|| SimulationConfig {
+        latency_ms: 200,
+        ..SimulationConfig::default()
+    });
+    let config = BacktestConfig {
+        simulation: SimulationConfig {
+            sz_decimals: args.sz_decimals,
+            ..simulation
+        },
+        ..BacktestConfig::default()
+    };
+
+    let mut backtester = Backtester::new(input, config)
+        .await
+        .expect("Failed to create Backtester");
+    let report = backtester.run(messages).await;
+    info!(
+        "Backtest of {} on {}:\n{report}",
+        market.asset,
+        args.recording.display()
+    );
+}
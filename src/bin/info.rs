@@ -1,22 +1,31 @@
 use ethers::types::H160;
+use futures_util::TryStreamExt;
 use hyperliquid_rust_sdk::{BaseUrl, InfoClient};
-use log::info;
 use std::str::FromStr;
+use tracing::info;
 
 #[tokio::main]
 async fn main() {
     env_logger::init();
     let info_client = InfoClient::new(None, Some(BaseUrl::Testnet)).await.unwrap();
     open_orders_example(&info_client).await;
+    frontend_open_orders_example(&info_client).await;
+    historical_orders_example(&info_client).await;
+    user_fees_example(&info_client).await;
+    user_funding_example(&info_client).await;
+    ledger_updates_example(&info_client).await;
     user_state_example(&info_client).await;
     user_states_example(&info_client).await;
     recent_trades(&info_client).await;
     meta_example(&info_client).await;
+    meta_and_asset_ctxs_example(&info_client).await;
     all_mids_example(&info_client).await;
     user_fills_example(&info_client).await;
+    user_fills_by_time_example(&info_client).await;
     funding_history_example(&info_client).await;
     l2_snapshot_example(&info_client).await;
     candles_snapshot_example(&info_client).await;
+    candles_stream_example(&info_client).await;
 }
 
 async fn open_orders_example(info_client: &InfoClient) {
@@ -31,6 +40,64 @@ async fn open_orders_example(info_client: &InfoClient) {
     );
 }
 
+async fn frontend_open_orders_example(info_client: &InfoClient) {
+    let user = H160::from_str("0xc64cc00b46101bd40aa1c3121195e85c0b0918d8").unwrap();
+
+    info!(
+        "Open orders with trigger details for {user}: {:?}",
+        info_client.frontend_open_orders(user).await.unwrap()
+    );
+}
+
+async fn historical_orders_example(info_client: &InfoClient) {
+    let user = H160::from_str("0xc64cc00b46101bd40aa1c3121195e85c0b0918d8").unwrap();
+
+    info!(
+        "Historical orders for {user}: {:?}",
+        info_client.historical_orders(user).await.unwrap()
+    );
+}
+
+async fn user_fees_example(info_client: &InfoClient) {
+    let user = H160::from_str("0xc64cc00b46101bd40aa1c3121195e85c0b0918d8").unwrap();
+
+    info!(
+        "Fees for {user}: {:?}",
+        info_client.user_fees(user).await.unwrap()
+    );
+    info!(
+        "Rate limit for {user}: {:?}",
+        info_client.user_rate_limit(user).await.unwrap()
+    );
+}
+
+async fn user_funding_example(info_client: &InfoClient) {
+    let user = H160::from_str("0xc64cc00b46101bd40aa1c3121195e85c0b0918d8").unwrap();
+    let start_timestamp = 1690540602225;
+    let end_timestamp = 1690569402225;
+
+    info!(
+        "Funding payments for {user} between timestamps {start_timestamp} and {end_timestamp}: {:?}",
+        info_client
+            .user_funding(user, start_timestamp, Some(end_timestamp))
+            .await
+            .unwrap()
+    );
+}
+
+async fn ledger_updates_example(info_client: &InfoClient) {
+    let user = H160::from_str("0xc64cc00b46101bd40aa1c3121195e85c0b0918d8").unwrap();
+    let start_timestamp = 1690540602225;
+
+    info!(
+        "Ledger updates for {user} since timestamp {start_timestamp}: {:?}",
+        info_client
+            .user_non_funding_ledger_updates(user, start_timestamp, None)
+            .await
+            .unwrap()
+    );
+}
+
 async fn user_state_example(info_client: &InfoClient) {
     let user: String = "0xc64cc00b46101bd40aa1c3121195e85c0b0918d8"
         .parse()
@@ -68,6 +135,13 @@ async fn meta_example(info_client: &InfoClient) {
     info!("Metadata: {:?}", info_client.meta().await.unwrap());
 }
 
+async fn meta_and_asset_ctxs_example(info_client: &InfoClient) {
+    info!(
+        "Metadata and asset contexts: {:?}",
+        info_client.meta_and_asset_ctxs().await.unwrap()
+    );
+}
+
 async fn all_mids_example(info_client: &InfoClient) {
     info!("All mids: {:?}", info_client.all_mids().await.unwrap());
 }
@@ -84,6 +158,24 @@ async fn user_fills_example(info_client: &InfoClient) {
     );
 }
 
+async fn user_fills_by_time_example(info_client: &InfoClient) {
+    let user: String = "0xc64cc00b46101bd40aa1c3121195e85c0b0918d8"
+        .parse()
+        .unwrap();
+    let user = H160::from_str(&user).unwrap();
+
+    let start_timestamp = 1690540602225;
+    let end_timestamp = 1690569402225;
+    info!(
+        "User fills data for {user} between timestamps {start_timestamp} and {end_timestamp}: {:?}",
+        info_client
+            .user_fills_stream(user, start_timestamp, end_timestamp)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+    );
+}
+
 async fn funding_history_example(info_client: &InfoClient) {
     let coin = "ETH";
 
@@ -102,6 +194,15 @@ async fn l2_snapshot_example(info_client: &InfoClient) {
         "L2 snapshot data for {coin}: {:?}",
         info_client.l2_snapshot(coin.to_string()).await.unwrap()
     );
+
+    let coin = "kPEPE";
+    info!(
+        "L2 snapshot data for {coin} aggregated to 3 significant figures: {:?}",
+        info_client
+            .l2_snapshot_aggregated(coin.to_string(), Some(3), None)
+            .await
+            .unwrap()
+    );
 }
 
 async fn candles_snapshot_example(info_client: &InfoClient) {
@@ -118,3 +219,25 @@ async fn candles_snapshot_example(info_client: &InfoClient) {
             .unwrap()
     );
 }
+
+async fn candles_stream_example(info_client: &InfoClient) {
+    let coin = "ETH";
+    let start_timestamp = 1687996800000;
+    let end_timestamp = 1690569402225;
+    let interval = "15m";
+
+    let candles = info_client
+        .candles_stream(
+            coin.to_string(),
+            interval.to_string(),
+            start_timestamp,
+            end_timestamp,
+        )
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    info!(
+        "{} candles for {coin} between timestamps {start_timestamp} and {end_timestamp} with interval {interval}",
+        candles.len()
+    );
+}
@@ -1,15 +1,13 @@
-use ethers::signers::{LocalWallet, Signer};
-use hyperliquid_rust_sdk::{BaseUrl, ExchangeClient, InfoClient};
-use log::info;
+use ethers::signers::Signer;
+use hyperliquid_rust_sdk::{load_wallet, BaseUrl, ExchangeClient, InfoClient};
+use tracing::info;
 
 #[tokio::main]
 async fn main() {
     // Example assumes you already have a position on ETH so you can update margin
     env_logger::init();
-    // Key was randomly generated for testing and shouldn't be used with any real funds
-    let wallet: LocalWallet = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
-        .parse()
-        .unwrap();
+    // Reads the keystore at HL_KEYSTORE, or the private key in HL_PRIVATE_KEY
+    let wallet = load_wallet().unwrap();
 
     let address = wallet.address();
     let exchange_client = ExchangeClient::new(None, wallet, Some(BaseUrl::Testnet), None, None)
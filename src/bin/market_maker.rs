@@ -1,103 +1,244 @@
 #![warn(clippy::all, clippy::nursery, clippy::pedantic)]
 
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
 use ethers::signers::LocalWallet;
-use hyperliquid_rust_sdk::{Input, MarketMaker};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use hyperliquid_rust_sdk::{
+    serve_metrics, write_event_log, ActionSigner, AgentConfig, AgentManager, AgentSigner, BaseUrl,
+    ConfigWatcher, EventRecord, ExchangeClient, InfoClient, Input, LogFormat, LoggingConfig,
+    MarketConfig, MarketMaker, MarketMakerConfig, PaperExchange, SimulationConfig,
+    WebsocketOrdersConfig,
+};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedSender},
+    watch,
+};
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+
+/// How often the config file is checked for parameter changes.
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+const USAGE: &str = "usage: market_maker --config <path>";
 
 #[tokio::main]
 async fn main() {
-    env_logger::init();
-
-    // Key was randomly generated for testing and shouldn't be used with any real funds
-    let wallet: LocalWallet = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
-        .parse()
-        .unwrap();
-
-    // Define a vector of market maker configurations
-    let market_makers = vec![
-        // SOL Market Maker
-        Input {
-            asset: "SOL".to_string(),
-            target_liquidity: 0.1,
-            max_bps_diff: 10,
-            half_spread: 5,
-            max_absolute_position_size: 2.0,
-            decimals: 2,
-            wallet: wallet.clone(),
-        },
-        // ETH Market Maker
-        Input {
-            asset: "ETH".to_string(),
-            target_liquidity: 0.003,
-            max_bps_diff: 10,
-            half_spread: 5,
-            max_absolute_position_size: 0.06,
-            decimals: 1,
-            wallet: wallet.clone(),
-        },
-        // BTC Market Maker
-        Input {
-            asset: "BTC".to_string(),
-            target_liquidity: 0.0002,
-            max_bps_diff: 10,
-            half_spread: 5,
-            max_absolute_position_size: 0.004,
-            decimals: 0,
-            wallet: wallet.clone(),
-        },
-        // ARB Market Maker
-        Input {
-            asset: "ARB".to_string(),
-            target_liquidity: 12.0,
-            max_bps_diff: 10,
-            half_spread: 5,
-            max_absolute_position_size: 240.0,
-            decimals: 4,
-            wallet: wallet.clone(),
-        },
-        // kPEPE Market Maker
-        Input {
-            asset: "kPEPE".to_string(),
-            target_liquidity: 1100.0,
-            max_bps_diff: 16,
-            half_spread: 8,
-            max_absolute_position_size: 20000.0,
-            decimals: 5,
-            wallet: wallet.clone(),
-        },
-        // RNDR Market Maker
-        Input {
-            asset: "RNDR".to_string(),
-            target_liquidity: 1.5,
-            max_bps_diff: 10,
-            half_spread: 5,
-            max_absolute_position_size: 30.0,
-            decimals: 3,
-            wallet,
+    let (path, config) = match config_path().and_then(|path| {
+        let config = MarketMakerConfig::load(&path).map_err(|e| e.to_string())?;
+        Ok((path, config))
+    }) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+
+    init_logging(&config.logging);
+
+    let wallet = match config.wallet.load() {
+        Ok(wallet) => wallet,
+        Err(e) => {
+            error!("Failed to load wallet: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    // Orders are signed by the agent if one is configured, and by the wallet
+    // otherwise
+    let signer: Arc<dyn ActionSigner> = match (&config.agent, &config.paper) {
+        (Some(agent), None) => match start_agent(agent, &wallet, config.network).await {
+            Ok(signer) => signer,
+            Err(e) => {
+                error!("Failed to set up agent {}: {e}", agent.name);
+                std::process::exit(1);
+            }
         },
-    ];
+        _ => Arc::new(wallet.clone()),
+    };
+
+    if let Some(address) = config.metrics_address {
+        tokio::spawn(async move {
+            if let Err(e) = serve_metrics(address).await {
+                error!("Metrics server failed: {e}");
+            }
+        });
+    }
+
+    let events = config.event_log.clone().map(|path| {
+        let (sender, receiver) = unbounded_channel();
+        tokio::spawn(async move {
+            if let Err(e) = write_event_log(&path, receiver).await {
+                error!("Event log failed: {e}");
+            }
+        });
+        sender
+    });
+
+    // Strategy parameters are reloaded when the config file changes
+    let watcher = match ConfigWatcher::new(path, &config) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            error!("Failed to watch config: {e}");
+            std::process::exit(1);
+        }
+    };
 
     // Create and start each market maker in a separate task
-    let tasks = market_makers
+    let tasks = config
+        .inputs(&wallet)
         .into_iter()
         .map(|input| {
-            let wallet = Arc::new(Mutex::new(input.wallet));
+            let network = config.network;
+            let paper = config.paper.clone();
+            let signer = signer.clone();
+            let order_timeout = config
+                .websocket_orders
+                .as_ref()
+                .map(WebsocketOrdersConfig::timeout);
+            let journal = config.journal_path(&input.asset);
+            let events = events.clone();
+            let updates = watcher.subscribe(&input.asset);
             tokio::spawn(async move {
-                MarketMaker::new(Input {
-                    wallet: wallet.lock().await.clone(),
-                    ..input
-                })
-                .await
-                .expect("Failed to create MarketMaker")
-                .start()
-                .await;
+                if let Some(simulation) = paper {
+                    run_paper(input, simulation, network, events, updates).await;
+                } else {
+                    let live = Live {
+                        network,
+                        signer,
+                        order_timeout,
+                    };
+                    run_live(input, live, journal, events, updates).await;
+                }
             })
         })
         .collect::<Vec<_>>();
+    tokio::spawn(watcher.run(CONFIG_POLL_INTERVAL));
 
     // Wait for all tasks to complete
     for task in tasks {
         task.await.unwrap();
     }
 }
+
+/// Returns the path given with `--config`.
+fn config_path() -> Result<PathBuf, String> {
+    let mut args = std::env::args().skip(1);
+    match (args.next().as_deref(), args.next(), args.next()) {
+        (Some("--config"), Some(path), None) => Ok(path.into()),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// Installs a `tracing` subscriber that writes to stderr in the configured
+/// format. Records of crates logging through `log` are included.
+fn init_logging(config: &LoggingConfig) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.level));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    match config.format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+/// Loads or approves the configured agent of `wallet` and keeps rotating it
+/// in the background.
+async fn start_agent(
+    config: &AgentConfig,
+    wallet: &LocalWallet,
+    network: BaseUrl,
+) -> Result<Arc<dyn ActionSigner>, Box<dyn std::error::Error>> {
+    let exchange_client =
+        ExchangeClient::new(None, wallet.clone(), Some(network), None, None).await?;
+    let manager = AgentManager::new(exchange_client, config.store()?, config.rotation_interval());
+    let agent = manager.agent(&config.name).await?;
+    info!(
+        "Trading through agent {} ({:?})",
+        agent.name,
+        ActionSigner::address(&agent.wallet)
+    );
+    let signer = Arc::new(AgentSigner::new(agent.wallet));
+    tokio::spawn(manager.run(config.name.clone(), signer.clone()));
+    Ok(signer)
+}
+
+/// How a live market maker connects to the exchange.
+struct Live {
+    network: BaseUrl,
+    signer: Arc<dyn ActionSigner>,
+    /// Response timeout of orders and cancels sent over a websocket, which
+    /// are sent as HTTP requests if it's `None`.
+    order_timeout: Option<Duration>,
+}
+
+/// Creates a market maker for `input` that trades on the exchange.
+async fn live_market_maker(
+    input: Input,
+    live: Live,
+) -> Result<MarketMaker, Box<dyn std::error::Error>> {
+    let Some(order_timeout) = live.order_timeout else {
+        return MarketMaker::with_signer(input, live.network, live.signer).await;
+    };
+    let info_client = InfoClient::new(None, Some(live.network)).await?;
+    let mut exchange_client = ExchangeClient::with_signer(
+        None,
+        live.signer,
+        Some(live.network),
+        None,
+        input.vault_address,
+    )
+    .await?;
+    exchange_client.use_websocket(order_timeout).await?;
+    MarketMaker::with_clients(input, info_client, exchange_client).await
+}
+
+/// Runs a market maker trading on the exchange.
+async fn run_live(
+    input: Input,
+    live: Live,
+    journal: Option<PathBuf>,
+    events: Option<UnboundedSender<EventRecord>>,
+    updates: Option<watch::Receiver<MarketConfig>>,
+) {
+    let mut market_maker = live_market_maker(input, live)
+        .await
+        .expect("Failed to create MarketMaker");
+    if let Some(journal) = journal {
+        market_maker
+            .restore_from_journal(&journal)
+            .expect("Failed to restore from journal");
+    }
+    if let Some(events) = events {
+        market_maker.set_event_sink(events);
+    }
+    if let Some(updates) = updates {
+        market_maker.set_config_updates(updates);
+    }
+    market_maker.start().await;
+}
+
+/// Runs a market maker on a paper account fed by live market data.
+async fn run_paper(
+    input: Input,
+    simulation: SimulationConfig,
+    network: BaseUrl,
+    events: Option<UnboundedSender<EventRecord>>,
+    updates: Option<watch::Receiver<MarketConfig>>,
+) {
+    let paper = PaperExchange::new(input.asset.clone(), simulation, network)
+        .await
+        .expect("Failed to create PaperExchange");
+    let mut market_maker = MarketMaker::with_clients(input, paper.clone(), paper)
+        .await
+        .expect("Failed to create MarketMaker");
+    if let Some(events) = events {
+        market_maker.set_event_sink(events);
+    }
+    if let Some(updates) = updates {
+        market_maker.set_config_updates(updates);
+    }
+    market_maker.start().await;
+}
@@ -1,19 +1,16 @@
-use ethers::signers::LocalWallet;
-use log::info;
+use tracing::info;
 
 use hyperliquid_rust_sdk::{
-    BaseUrl, ClientCancelRequest, ClientLimit, ClientOrder, ClientOrderRequest, ExchangeClient,
-    ExchangeDataStatus, ExchangeResponseStatus,
+    load_wallet, BaseUrl, ClientCancelRequest, ClientLimit, ClientOrder, ClientOrderRequest,
+    ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus,
 };
 use std::{thread::sleep, time::Duration};
 
 #[tokio::main]
 async fn main() {
     env_logger::init();
-    // Key was randomly generated for testing and shouldn't be used with any real funds
-    let wallet: LocalWallet = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
-        .parse()
-        .unwrap();
+    // Reads the keystore at HL_KEYSTORE, or the private key in HL_PRIVATE_KEY
+    let wallet = load_wallet().unwrap();
 
     let exchange_client = ExchangeClient::new(None, wallet, Some(BaseUrl::Testnet), None, None)
         .await
@@ -0,0 +1,255 @@
+#![warn(clippy::all, clippy::nursery, clippy::pedantic)]
+
+use std::{path::PathBuf, time::Duration};
+
+use futures_util::TryStreamExt;
+use hyperliquid_rust_sdk::{
+    now_timestamp_ms, BaseUrl, Candle, CandleData, InfoClient, Message, RecordedMessage,
+    RecordingWriter, Subscription,
+};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver},
+    time::{sleep, timeout},
+};
+use tracing::{error, info, warn};
+
+/// Reconnect when no message arrived for this long.
+const STALE_TIMEOUT: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+const USAGE: &str = "Usage: recorder [--coins ETH,BTC] [--channels l2Book,trades,candle,allMids] \
+[--candle-interval 1m] [--output recordings] [--rotate-minutes 60] [--backfill-hours 0] [--testnet]";
+
+struct Args {
+    coins: Vec<String>,
+    channels: Vec<String>,
+    candle_interval: String,
+    output: PathBuf,
+    rotation_interval_ms: u64,
+    backfill_hours: u64,
+    base_url: BaseUrl,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = Self {
+            coins: vec!["ETH".to_string()],
+            channels: ["l2Book", "trades", "candle", "allMids"]
+                .map(str::to_string)
+                .to_vec(),
+            candle_interval: "1m".to_string(),
+            output: PathBuf::from("recordings"),
+            rotation_interval_ms: 60 * 60 * 1000,
+            backfill_hours: 0,
+            base_url: BaseUrl::Mainnet,
+        };
+
+        let mut raw = std::env::args().skip(1);
+        while let Some(flag) = raw.next() {
+            if flag == "--testnet" {
+                args.base_url = BaseUrl::Testnet;
+                continue;
+            }
+            let value = raw
+                .next()
+                .ok_or_else(|| format!("Missing value for {flag}"))?;
+            let list = || value.split(',').map(str::to_string).collect::<Vec<_>>();
+            match flag.as_str() {
+                "--coins" => args.coins = list(),
+                "--channels" => args.channels = list(),
+                "--candle-interval" => args.candle_interval = value,
+                "--output" => args.output = value.into(),
+                "--rotate-minutes" => {
+                    let minutes: u64 = value.parse().map_err(|e| format!("{flag}: {e}"))?;
+                    if minutes == 0 {
+                        return Err(format!("{flag} must be positive"));
+                    }
+                    args.rotation_interval_ms = minutes
+                        .checked_mul(60 * 1000)
+                        .ok_or_else(|| format!("{flag}: {minutes} is too large"))?;
+                }
+                "--backfill-hours" => {
+                    args.backfill_hours = value.parse().map_err(|e| format!("{flag}: {e}"))?;
+                }
+                _ => return Err(format!("Unknown argument {flag}")),
+            }
+        }
+        Ok(args)
+    }
+
+    fn subscriptions(&self) -> Result<Vec<Subscription>, String> {
+        let mut subscriptions = Vec::new();
+        for channel in &self.channels {
+            match channel.as_str() {
+                "allMids" => subscriptions.push(Subscription::AllMids),
+                "l2Book" | "trades" | "candle" => {
+                    subscriptions.extend(self.coins.iter().map(|coin| match channel.as_str() {
+                        "l2Book" => Subscription::L2Book {
+                            coin: coin.clone(),
+                            n_sig_figs: None,
+                            mantissa: None,
+                        },
+                        "trades" => Subscription::Trades { coin: coin.clone() },
+                        _ => Subscription::Candle {
+                            coin: coin.clone(),
+                            interval: self.candle_interval.clone(),
+                        },
+                    }));
+                }
+                _ => return Err(format!("Unknown channel {channel}")),
+            }
+        }
+        Ok(subscriptions)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let args = Args::parse().unwrap_or_else(|e| panic!("{e}\n{USAGE}"));
+    let subscriptions = args
+        .subscriptions()
+        .unwrap_or_else(|e| panic!("{e}\n{USAGE}"));
+
+    if args.backfill_hours > 0 {
+        backfill_candles(&args).await;
+    }
+
+    let mut writer = RecordingWriter::new(
+        args.output.clone(),
+        "market-data".to_string(),
+        args.rotation_interval_ms,
+    )
+    .expect("Failed to create recording writer");
+
+    loop {
+        let mut info_client = match InfoClient::new(None, Some(args.base_url)).await {
+            Ok(info_client) => info_client,
+            Err(e) => {
+                error!("Error creating info client: {e}");
+                sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+        let (sender, receiver) = unbounded_channel();
+        let mut subscribed = true;
+        for subscription in &subscriptions {
+            if let Err(e) = info_client
+                .subscribe(subscription.clone(), sender.clone())
+                .await
+            {
+                error!("Error subscribing to {subscription:?}: {e}");
+                subscribed = false;
+                break;
+            }
+        }
+        drop(sender);
+
+        if subscribed {
+            info!("Recording {} subscriptions", subscriptions.len());
+            if !record(receiver, &mut writer).await {
+                break;
+            }
+        }
+        // Dropping the client closes the old connection before we reconnect
+        drop(info_client);
+        warn!("Reconnecting in {RECONNECT_DELAY:?}");
+        sleep(RECONNECT_DELAY).await;
+    }
+
+    if let Err(e) = writer.finish() {
+        error!("Error finishing recording: {e}");
+    }
+}
+
+/// Writes messages until the connection goes stale. Returns `false` when the
+/// recorder should shut down.
+async fn record(mut receiver: UnboundedReceiver<Message>, writer: &mut RecordingWriter) -> bool {
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {
+                info!("Shutting down recorder");
+                return false;
+            }
+            message = timeout(STALE_TIMEOUT, receiver.recv()) => {
+                let message = match message {
+                    Ok(Some(message)) => message,
+                    Ok(None) => {
+                        warn!("Subscription channel closed");
+                        return true;
+                    }
+                    Err(_) => {
+                        warn!("No messages received for {STALE_TIMEOUT:?}");
+                        return true;
+                    }
+                };
+                if matches!(message, Message::SubscriptionResponse) {
+                    continue;
+                }
+                let recorded = RecordedMessage { time: now_timestamp_ms(), message };
+                if let Err(e) = writer.write(&recorded) {
+                    error!("Error writing recorded message: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Fetches candles for the backfill window and stores them tagged with their
+/// close time, in a separate file.
+async fn backfill_candles(args: &Args) {
+    let info_client = InfoClient::new(None, Some(args.base_url))
+        .await
+        .expect("Failed to create info client");
+    let mut writer = RecordingWriter::new(
+        args.output.clone(),
+        "candles-backfill".to_string(),
+        u64::MAX,
+    )
+    .expect("Failed to create recording writer");
+    let end_time = now_timestamp_ms();
+    let start_time = end_time.saturating_sub(args.backfill_hours * 60 * 60 * 1000);
+
+    for coin in &args.coins {
+        let candles = match info_client
+            .candles_stream(
+                coin.clone(),
+                args.candle_interval.clone(),
+                start_time,
+                end_time,
+            )
+            .try_collect::<Vec<_>>()
+            .await
+        {
+            Ok(candles) => candles,
+            Err(e) => {
+                error!("Error backfilling candles for {coin}: {e}");
+                continue;
+            }
+        };
+        info!("Backfilling {} candles for {coin}", candles.len());
+        for candle in candles {
+            let recorded = RecordedMessage {
+                time: candle.time_close,
+                message: Message::Candle(Candle {
+                    data: CandleData {
+                        time_close: candle.time_close,
+                        close: candle.close,
+                        high: candle.high,
+                        interval: candle.candle_interval,
+                        low: candle.low,
+                        num_trades: candle.num_trades,
+                        open: candle.open,
+                        coin: candle.coin,
+                        time_open: candle.time_open,
+                        volume: candle.vlm,
+                    },
+                }),
+            };
+            if let Err(e) = writer.write(&recorded) {
+                error!("Error writing backfilled candle: {e}");
+            }
+        }
+    }
+}
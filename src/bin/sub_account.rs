@@ -0,0 +1,35 @@
+use hyperliquid_rust_sdk::{load_wallet, BaseUrl, CreateSubAccountResponseStatus, ExchangeClient};
+use tracing::info;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Reads the keystore at HL_KEYSTORE, or the private key in HL_PRIVATE_KEY
+    let wallet = load_wallet().unwrap();
+
+    let exchange_client = ExchangeClient::new(None, wallet, Some(BaseUrl::Testnet), None, None)
+        .await
+        .unwrap();
+
+    let res = exchange_client
+        .create_sub_account("market_maker", None)
+        .await
+        .unwrap();
+    info!("Create subaccount result: {res:?}");
+    let CreateSubAccountResponseStatus::Ok(response) = res else {
+        return;
+    };
+
+    // Move 1 USD to the new subaccount and back
+    let sub_account_user = response.data;
+    let res = exchange_client
+        .sub_account_transfer(sub_account_user, true, 1.0, None)
+        .await
+        .unwrap();
+    info!("Deposit result: {res:?}");
+    let res = exchange_client
+        .sub_account_transfer(sub_account_user, false, 1.0, None)
+        .await
+        .unwrap();
+    info!("Withdrawal result: {res:?}");
+}
@@ -1,4 +1,4 @@
-use log::info;
+use tracing::info;
 
 use hyperliquid_rust_sdk::{BaseUrl, InfoClient, Message, Subscription};
 use tokio::{
@@ -18,6 +18,8 @@ async fn main() {
         .subscribe(
             Subscription::L2Book {
                 coin: "ETH".to_string(),
+                n_sig_figs: None,
+                mantissa: None,
             },
             sender,
         )
@@ -1,4 +1,4 @@
-use log::info;
+use tracing::info;
 
 use hyperliquid_rust_sdk::{BaseUrl, InfoClient, Message, Subscription};
 use tokio::{
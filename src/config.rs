@@ -0,0 +1,667 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ethers::{signers::LocalWallet, types::H160};
+use serde::Deserialize;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+use crate::{
+    agents::is_valid_agent_name, prelude::*, wallet_from_env, wallet_from_file,
+    wallet_from_keystore, AgentStore, BaseUrl, Error, Input, KeystorePassword, SimulationConfig,
+};
+
+/// Configuration of the `market_maker` executable, loaded from a TOML or
+/// YAML file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketMakerConfig {
+    pub network: BaseUrl,
+    pub wallet: WalletConfig,
+    /// Vault or subaccount to trade for. The wallet, or the agent if one is
+    /// configured, signs for it as its leader or master.
+    #[serde(default)]
+    pub vault_address: Option<H160>,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Paper trade with these simulation parameters instead of sending
+    /// orders to the exchange.
+    #[serde(default)]
+    pub paper: Option<SimulationConfig>,
+    /// Trade through a named agent of the wallet instead of signing orders
+    /// with the wallet itself.
+    #[serde(default)]
+    pub agent: Option<AgentConfig>,
+    /// Send orders and cancels over a websocket connection instead of HTTP
+    /// requests.
+    #[serde(default)]
+    pub websocket_orders: Option<WebsocketOrdersConfig>,
+    /// Directory of the order journals the market makers restore their
+    /// state from after a restart, one per asset. Not supported in paper
+    /// trading, which starts from a fresh account.
+    #[serde(default)]
+    pub journal_directory: Option<PathBuf>,
+    /// JSON lines file the trading events of all market makers are appended
+    /// to.
+    #[serde(default)]
+    pub event_log: Option<PathBuf>,
+    /// Address to serve Prometheus metrics on at `/metrics`.
+    #[serde(default)]
+    pub metrics_address: Option<SocketAddr>,
+    pub markets: Vec<MarketConfig>,
+}
+
+/// Where the private key of the trading wallet is read from.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case", deny_unknown_fields)]
+pub enum WalletConfig {
+    /// Environment variable holding the hex encoded private key.
+    Env { variable: String },
+    /// File holding the hex encoded private key.
+    File { path: PathBuf },
+    /// Encrypted JSON keystore. The passphrase is read from `password_env`
+    /// or `password_file`, or prompted for if neither is set.
+    Keystore {
+        path: PathBuf,
+        password_env: Option<String>,
+        password_file: Option<PathBuf>,
+    },
+}
+
+/// Agent wallet approved by the configured wallet to trade for it. Its keys
+/// are kept as encrypted keystores in `directory`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentConfig {
+    pub name: String,
+    pub directory: PathBuf,
+    /// Replace the agent with a new one after this many hours. Agents are
+    /// kept until removed from `directory` if unset.
+    pub rotation_hours: Option<u64>,
+    /// The keystore passphrase is read from `password_env` or
+    /// `password_file`, or prompted for if neither is set.
+    pub password_env: Option<String>,
+    pub password_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebsocketOrdersConfig {
+    /// How long to wait for the response to an order or cancel.
+    pub timeout_ms: u64,
+}
+
+impl Default for WebsocketOrdersConfig {
+    fn default() -> Self {
+        Self { timeout_ms: 5000 }
+    }
+}
+
+impl WebsocketOrdersConfig {
+    #[must_use]
+    pub const fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Filters in `env_logger` syntax, optionally with span fields as
+    /// accepted by `tracing_subscriber::EnvFilter`. `RUST_LOG` takes
+    /// precedence.
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines, prefixed with the fields of the enclosing
+    /// spans.
+    #[default]
+    Text,
+    /// One JSON object per line, with the enclosing spans under `spans`.
+    Json,
+}
+
+/// Strategy parameters and risk limits of a single market maker.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketConfig {
+    pub asset: String,
+    pub target_liquidity: f64,
+    pub half_spread: u16,
+    pub max_bps_diff: u16,
+    pub max_absolute_position_size: f64,
+    pub decimals: u32,
+    #[serde(default = "default_leverage")]
+    pub leverage: u32,
+    #[serde(default = "default_is_cross")]
+    pub is_cross: bool,
+    #[serde(default = "default_max_margin_utilization")]
+    pub max_margin_utilization: f64,
+    #[serde(default = "default_min_liquidation_distance_bps")]
+    pub min_liquidation_distance_bps: u16,
+    #[serde(default)]
+    pub funding_skew_hours: f64,
+}
+
+const fn default_leverage() -> u32 {
+    3
+}
+
+const fn default_is_cross() -> bool {
+    true
+}
+
+const fn default_max_margin_utilization() -> f64 {
+    0.5
+}
+
+const fn default_min_liquidation_distance_bps() -> u16 {
+    1000
+}
+
+impl MarketMakerConfig {
+    /// Reads and validates a config file. Files ending in `.yaml` or `.yml`
+    /// are parsed as YAML, everything else as TOML.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file can't be read, doesn't parse or fails
+    /// validation.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("{}: {e}", path.display())))?;
+        let is_yaml = path
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml");
+        let config = if is_yaml {
+            Self::from_yaml(&contents)
+        } else {
+            Self::from_toml(&contents)
+        };
+        config.map_err(|e| Error::Config(format!("{}: {e}", path.display())))
+    }
+
+    /// Parses and validates a TOML config.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the config doesn't parse or fails validation.
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        let config: Self = toml::from_str(contents).map_err(|e| Error::Config(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parses and validates a YAML config.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the config doesn't parse or fails validation.
+    pub fn from_yaml(contents: &str) -> Result<Self> {
+        let config: Self =
+            serde_yaml::from_str(contents).map_err(|e| Error::Config(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the parameters for values the market maker can't run with,
+    /// reporting all problems at once.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` listing every invalid parameter.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        if self.markets.is_empty() {
+            problems.push("no markets configured".to_string());
+        }
+        let mut assets = HashSet::new();
+        for (i, market) in self.markets.iter().enumerate() {
+            let name = format!("markets[{i}] ({})", market.asset);
+            if market.asset.is_empty() {
+                problems.push(format!("{name}: asset must not be empty"));
+            } else if !assets.insert(market.asset.as_str()) {
+                problems.push(format!("{name}: asset is configured more than once"));
+            }
+            problems.extend(
+                market
+                    .problems()
+                    .into_iter()
+                    .map(|problem| format!("{name}: {problem}")),
+            );
+        }
+        if let Some(paper) = &self.paper {
+            if !(0.0..=1.0).contains(&paper.queue_position) {
+                problems.push("paper: queue_position must be between 0 and 1".to_string());
+            }
+            if paper.initial_balance <= 0.0 {
+                problems.push("paper: initial_balance must be positive".to_string());
+            }
+            if self.journal_directory.is_some() {
+                problems.push("journal_directory is not supported in paper trading".to_string());
+            }
+        }
+        if let Some(agent) = &self.agent {
+            if !is_valid_agent_name(&agent.name) {
+                problems
+                    .push("agent: name must only contain letters, digits, '_' and '-'".to_string());
+            }
+            if agent.rotation_hours == Some(0) {
+                problems.push("agent: rotation_hours must be positive".to_string());
+            }
+        }
+        if self
+            .websocket_orders
+            .as_ref()
+            .is_some_and(|ws| ws.timeout_ms == 0)
+        {
+            problems.push("websocket_orders: timeout_ms must be positive".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Config(problems.join("; ")))
+        }
+    }
+
+    /// The journal of the market maker for `asset`, if journaling is
+    /// enabled.
+    #[must_use]
+    pub fn journal_path(&self, asset: &str) -> Option<PathBuf> {
+        self.journal_directory
+            .as_ref()
+            .map(|directory| directory.join(format!("{}.jsonl", asset.replace('/', "_"))))
+    }
+
+    /// The market maker inputs of all configured markets.
+    #[must_use]
+    pub fn inputs(&self, wallet: &LocalWallet) -> Vec<Input> {
+        self.markets
+            .iter()
+            .map(|market| Input {
+                vault_address: self.vault_address,
+                ..market.input(wallet.clone())
+            })
+            .collect()
+    }
+}
+
+impl MarketConfig {
+    pub(crate) fn problems(&self) -> Vec<&'static str> {
+        let mut problems = Vec::new();
+        if !(self.target_liquidity > 0.0 && self.target_liquidity.is_finite()) {
+            problems.push("target_liquidity must be positive");
+        }
+        if self.half_spread == 0 {
+            problems.push("half_spread must be positive");
+        }
+        if self.max_bps_diff == 0 {
+            problems.push("max_bps_diff must be positive");
+        }
+        if !(self.max_absolute_position_size > 0.0 && self.max_absolute_position_size.is_finite()) {
+            problems.push("max_absolute_position_size must be positive");
+        }
+        if self.decimals > 8 {
+            problems.push("decimals must be at most 8");
+        }
+        if self.leverage == 0 {
+            problems.push("leverage must be at least 1");
+        }
+        if !(self.max_margin_utilization > 0.0 && self.max_margin_utilization <= 1.0) {
+            problems.push("max_margin_utilization must be in (0, 1]");
+        }
+        if !(self.funding_skew_hours >= 0.0 && self.funding_skew_hours.is_finite()) {
+            problems.push("funding_skew_hours must not be negative");
+        }
+        problems
+    }
+
+    #[must_use]
+    pub fn input(&self, wallet: LocalWallet) -> Input {
+        Input {
+            asset: self.asset.clone(),
+            target_liquidity: self.target_liquidity,
+            half_spread: self.half_spread,
+            max_bps_diff: self.max_bps_diff,
+            max_absolute_position_size: self.max_absolute_position_size,
+            decimals: self.decimals,
+            leverage: self.leverage,
+            is_cross: self.is_cross,
+            max_margin_utilization: self.max_margin_utilization,
+            min_liquidation_distance_bps: self.min_liquidation_distance_bps,
+            funding_skew_hours: self.funding_skew_hours,
+            wallet,
+            vault_address: None,
+        }
+    }
+}
+
+/// Polls a config file and passes changed market settings to the running
+/// market makers through `watch` channels.
+///
+/// Only strategy parameters and risk limits are reloaded. Changes to the
+/// network, wallet, paper trading or the set of markets need a restart.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    contents: String,
+    markets: HashMap<String, watch::Sender<MarketConfig>>,
+}
+
+impl ConfigWatcher {
+    /// Creates a watcher for the file `config` was loaded from.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file can't be read.
+    pub fn new(path: PathBuf, config: &MarketMakerConfig) -> Result<Self> {
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| Error::Config(format!("{}: {e}", path.display())))?;
+        let markets = config
+            .markets
+            .iter()
+            .map(|market| (market.asset.clone(), watch::channel(market.clone()).0))
+            .collect();
+        Ok(Self {
+            path,
+            contents,
+            markets,
+        })
+    }
+
+    /// Receives the settings of `asset` whenever they change.
+    #[must_use]
+    pub fn subscribe(&self, asset: &str) -> Option<watch::Receiver<MarketConfig>> {
+        self.markets.get(asset).map(watch::Sender::subscribe)
+    }
+
+    /// Reloads the file if it changed, returning whether any market settings
+    /// were updated.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the changed file can't be read or is invalid, in
+    /// which case the previous settings stay in effect.
+    pub fn reload(&mut self) -> Result<bool> {
+        let contents = fs::read_to_string(&self.path)
+            .map_err(|e| Error::Config(format!("{}: {e}", self.path.display())))?;
+        if contents == self.contents {
+            return Ok(false);
+        }
+        self.contents = contents;
+        let config = MarketMakerConfig::load(&self.path)?;
+
+        let mut updated = false;
+        for market in config.markets {
+            match self.markets.get(&market.asset) {
+                Some(sender) => {
+                    updated |= sender.send_if_modified(|current| {
+                        let modified = *current != market;
+                        *current = market;
+                        modified
+                    });
+                }
+                None => warn!(
+                    "New market {} in {} needs a restart",
+                    market.asset,
+                    self.path.display()
+                ),
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Reloads the file every `interval` until all market makers are gone.
+    pub async fn run(mut self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        while self.markets.values().any(|sender| !sender.is_closed()) {
+            ticker.tick().await;
+            match self.reload() {
+                Ok(true) => info!("Reloaded {}", self.path.display()),
+                Ok(false) => {}
+                Err(e) => error!("Keeping previous config: {e}"),
+            }
+        }
+    }
+}
+
+impl AgentConfig {
+    /// Opens the keystore directory, reading the passphrase.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the directory can't be created or the passphrase
+    /// can't be read.
+    pub fn store(&self) -> Result<AgentStore> {
+        AgentStore::new(
+            self.directory.clone(),
+            &keystore_password(&self.password_env, &self.password_file),
+        )
+    }
+
+    /// How long an agent is used before it's replaced.
+    #[must_use]
+    pub fn rotation_interval(&self) -> Option<Duration> {
+        self.rotation_hours
+            .map(|hours| Duration::from_secs(hours * 60 * 60))
+    }
+}
+
+fn keystore_password(
+    password_env: &Option<String>,
+    password_file: &Option<PathBuf>,
+) -> KeystorePassword {
+    match (password_env, password_file) {
+        (Some(variable), _) => KeystorePassword::Env(variable.clone()),
+        (None, Some(file)) => KeystorePassword::File(file.clone()),
+        (None, None) => KeystorePassword::Prompt,
+    }
+}
+
+impl WalletConfig {
+    /// Reads the private key from the configured source.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the key can't be read or isn't a valid private key.
+    pub fn load(&self) -> Result<LocalWallet> {
+        match self {
+            Self::Env { variable } => wallet_from_env(variable),
+            Self::File { path } => wallet_from_file(path),
+            Self::Keystore {
+                path,
+                password_env,
+                password_file,
+            } => wallet_from_keystore(path, &keystore_password(password_env, password_file)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKET: &str = r#"
+network = "testnet"
+
+[wallet]
+source = "file"
+path = "key.txt"
+
+[[markets]]
+asset = "ETH"
+target_liquidity = 0.01
+half_spread = 5
+max_bps_diff = 10
+max_absolute_position_size = 0.1
+decimals = 1
+"#;
+
+    #[test]
+    fn test_example_config_is_valid() {
+        let config =
+            MarketMakerConfig::from_toml(include_str!("../config/market_maker.toml")).unwrap();
+        assert_eq!(config.network, BaseUrl::Mainnet);
+        assert_eq!(config.markets.len(), 6);
+        assert!(config.paper.is_none());
+    }
+
+    #[test]
+    fn test_risk_limits_default() {
+        let config = MarketMakerConfig::from_toml(MARKET).unwrap();
+        let market = &config.markets[0];
+        assert_eq!(market.leverage, 3);
+        assert!(market.is_cross);
+        assert_eq!(market.min_liquidation_distance_bps, 1000);
+        assert_eq!(config.logging.level, "info");
+        assert_eq!(config.logging.format, LogFormat::Text);
+        assert!(matches!(config.wallet, WalletConfig::File { .. }));
+        assert!(config.agent.is_none());
+        assert!(config.journal_path("ETH").is_none());
+        assert!(config.websocket_orders.is_none());
+
+        let config = MarketMakerConfig::from_toml(
+            &MARKET.replace("[[markets]]", "[websocket_orders]\n\n[[markets]]"),
+        )
+        .unwrap();
+        assert_eq!(
+            config.websocket_orders.unwrap().timeout(),
+            Duration::from_secs(5)
+        );
+    }
+
+    #[test]
+    fn test_agent_config() {
+        let agent = "[agent]\nname = \"mm\"\ndirectory = \"agents\"\nrotation_hours = 24\n";
+        let config = MarketMakerConfig::from_toml(
+            &MARKET.replace("[[markets]]", &format!("{agent}\n[[markets]]")),
+        )
+        .unwrap();
+        let agent = config.agent.unwrap();
+        assert_eq!(agent.name, "mm");
+        assert_eq!(
+            agent.rotation_interval(),
+            Some(Duration::from_secs(24 * 60 * 60))
+        );
+
+        let invalid = "[agent]\nname = \"m m\"\ndirectory = \"agents\"\nrotation_hours = 0\n";
+        let e = MarketMakerConfig::from_toml(
+            &MARKET.replace("[[markets]]", &format!("{invalid}\n[[markets]]")),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(e.contains("agent: name must only contain"), "{e}");
+        assert!(e.contains("agent: rotation_hours must be positive"), "{e}");
+    }
+
+    #[test]
+    fn test_journal_is_rejected_in_paper_trading() {
+        let contents = format!(
+            "journal_directory = \"journals\"\n{}",
+            MARKET.replace("[[markets]]", "[paper]\n\n[[markets]]")
+        );
+        let e = MarketMakerConfig::from_toml(&contents)
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("journal_directory is not supported"), "{e}");
+
+        let contents = format!("journal_directory = \"journals\"\n{MARKET}");
+        assert!(MarketMakerConfig::from_toml(&contents).is_ok());
+    }
+
+    #[test]
+    fn test_yaml_config() {
+        let config = MarketMakerConfig::from_yaml(
+            r"
+network: mainnet
+wallet:
+  source: env
+  variable: HL_PRIVATE_KEY
+paper:
+  latency_ms: 50
+markets:
+  - asset: BTC
+    target_liquidity: 0.001
+    half_spread: 3
+    max_bps_diff: 6
+    max_absolute_position_size: 0.01
+    decimals: 0
+    leverage: 5
+",
+        )
+        .unwrap();
+        assert_eq!(config.markets[0].leverage, 5);
+        assert_eq!(config.paper.unwrap().latency_ms, 50);
+    }
+
+    #[test]
+    fn test_invalid_values_are_all_reported() {
+        let contents = format!(
+            "{MARKET}\n{}",
+            MARKET[MARKET.find("[[markets]]").unwrap()..]
+                .replace("half_spread = 5", "half_spread = 0")
+                .replace("decimals = 1", "decimals = 1\nmax_margin_utilization = 1.5")
+        );
+        let Err(Error::Config(message)) = MarketMakerConfig::from_toml(&contents) else {
+            panic!("config should be invalid");
+        };
+        assert_eq!(
+            message,
+            "markets[1] (ETH): asset is configured more than once; \
+             markets[1] (ETH): half_spread must be positive; \
+             markets[1] (ETH): max_margin_utilization must be in (0, 1]"
+        );
+    }
+
+    #[test]
+    fn test_watcher_sends_changed_markets_only() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", uuid::Uuid::new_v4()));
+        let write =
+            |contents: &str| fs::write(&path, contents).map_err(|e| Error::Io(e.to_string()));
+        write(MARKET)?;
+        let config = MarketMakerConfig::load(&path)?;
+        let mut watcher = ConfigWatcher::new(path.clone(), &config)?;
+        let mut updates = watcher.subscribe("ETH").ok_or(Error::AssetNotFound)?;
+        assert!(!watcher.reload()?);
+
+        write(&MARKET.replace("half_spread = 5", "half_spread = 7"))?;
+        assert!(watcher.reload()?);
+        assert!(updates.has_changed().unwrap_or(false));
+        assert_eq!(updates.borrow_and_update().half_spread, 7);
+
+        // Invalid files keep the previous settings
+        write(&MARKET.replace("half_spread = 5", "half_spread = 0"))?;
+        assert!(watcher.reload().is_err());
+        assert!(!updates.has_changed().unwrap_or(true));
+
+        fs::remove_file(&path).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        let contents = MARKET.replace("half_spread", "half_sprad");
+        assert!(matches!(
+            MarketMakerConfig::from_toml(&contents),
+            Err(Error::Config(message)) if message.contains("half_sprad")
+        ));
+    }
+}
@@ -9,7 +9,7 @@ pub enum Error {
         status_code: u16,
         error_code: Option<u16>,
         error_message: String,
-        headers: HeaderMap,
+        headers: Box<HeaderMap>,
         error_data: Option<String>,
     },
     #[error("Server error: status code: {status_code}, error message: {error_message}")]
@@ -51,12 +51,18 @@ pub enum Error {
     RandGen(String),
     #[error("Private key parse error: {0:?}")]
     PrivateKeyParse(String),
-    #[error("Cannot subscribe to multiple user events")]
-    UserEvents,
     #[error("Rmp parse error: {0:?}")]
     RmpParse(String),
     #[error("Invalid input number")]
     FloatStringParse,
     #[error("No cloid found in order request when expected")]
     NoCloid,
+    #[error("IO error: {0:?}")]
+    Io(String),
+    #[error("Config error: {0}")]
+    Config(String),
+    /// A request was sent over the websocket, but no response arrived, so it
+    /// may or may not have been executed.
+    #[error("No response to a websocket post, the request may have been executed: {0}")]
+    PostUnconfirmed(String),
 }
@@ -0,0 +1,186 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::{helpers::now_timestamp_ms, prelude::*, Error};
+
+lazy_static! {
+    // Seeded with the start time so ids stay unique across restarts
+    static ref NEXT_CORRELATION_ID: AtomicU64 = AtomicU64::new(now_timestamp_ms() << 16);
+}
+
+/// A new id that ties a quote decision to the orders, cancels and fills
+/// that follow from it.
+pub fn next_correlation_id() -> u64 {
+    NEXT_CORRELATION_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A trading decision of a market maker or its outcome.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TradingEvent {
+    /// Quotes computed from a new mid price, with the inputs they were
+    /// derived from. A side is only replaced if its `replace_` flag is set.
+    QuoteDecision {
+        mid: f64,
+        half_spread_bps: u16,
+        position: f64,
+        margin_utilization: f64,
+        bid_px: f64,
+        bid_sz: f64,
+        ask_px: f64,
+        ask_sz: f64,
+        /// Shift of the quotes below the mid price towards the side that
+        /// receives funding, in basis points.
+        funding_skew_bps: f64,
+        replace_bid: bool,
+        replace_ask: bool,
+    },
+    OrderSent {
+        is_buy: bool,
+        px: f64,
+        sz: f64,
+    },
+    OrderAck {
+        oid: u64,
+        is_buy: bool,
+        px: f64,
+        sz: f64,
+    },
+    OrderRejected {
+        is_buy: bool,
+        error: String,
+    },
+    CancelSent {
+        oid: u64,
+    },
+    Canceled {
+        oid: u64,
+    },
+    CancelFailed {
+        oid: u64,
+        error: String,
+    },
+    Fill {
+        oid: u64,
+        is_buy: bool,
+        px: f64,
+        sz: f64,
+        fee: f64,
+        closed_pnl: f64,
+        /// Position after the fill.
+        position: f64,
+    },
+}
+
+/// A trading event of the market maker for `asset`, with the local time in
+/// milliseconds at which it happened.
+///
+/// Events caused by the same quote decision share its `correlation_id`,
+/// including fills of the orders it placed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EventRecord {
+    pub time: u64,
+    pub asset: String,
+    pub correlation_id: u64,
+    #[serde(flatten)]
+    pub event: TradingEvent,
+}
+
+impl EventRecord {
+    pub fn new(asset: &str, correlation_id: u64, event: TradingEvent) -> Self {
+        Self {
+            time: now_timestamp_ms(),
+            asset: asset.to_string(),
+            correlation_id,
+            event,
+        }
+    }
+}
+
+/// Appends the records received from `receiver` to the JSON lines file at
+/// `path` until all senders are dropped. Records are flushed as soon as no
+/// more are waiting.
+pub async fn write_event_log(
+    path: &Path,
+    mut receiver: UnboundedReceiver<EventRecord>,
+) -> Result<()> {
+    let io_error = |e: std::io::Error| Error::Io(format!("{}: {e}", path.display()));
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(io_error)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(io_error)?;
+    let mut writer = BufWriter::new(file);
+
+    while let Some(record) = receiver.recv().await {
+        let mut record = Some(record);
+        while let Some(next) = record {
+            serde_json::to_writer(&mut writer, &next)
+                .map_err(|e| Error::JsonParse(e.to_string()))?;
+            writer.write_all(b"\n").map_err(io_error)?;
+            record = receiver.try_recv().ok();
+        }
+        writer.flush().map_err(io_error)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[tokio::test]
+    async fn test_event_log_is_written_as_json_lines() -> Result<()> {
+        let path = std::env::temp_dir()
+            .join(format!("events-{}", uuid::Uuid::new_v4()))
+            .join("events.jsonl");
+        let correlation_id = next_correlation_id();
+        assert!(next_correlation_id() > correlation_id);
+
+        let (sender, receiver) = unbounded_channel();
+        let records = vec![
+            EventRecord::new(
+                "ETH",
+                correlation_id,
+                TradingEvent::OrderSent {
+                    is_buy: true,
+                    px: 99.0,
+                    sz: 1.0,
+                },
+            ),
+            EventRecord::new("ETH", correlation_id, TradingEvent::Canceled { oid: 3 }),
+        ];
+        for record in records.clone() {
+            sender
+                .send(record)
+                .map_err(|e| Error::WsSend(e.to_string()))?;
+        }
+        drop(sender);
+        write_event_log(&path, receiver).await?;
+
+        let contents = fs::read_to_string(&path).map_err(|e| Error::Io(e.to_string()))?;
+        let lines = contents.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains(r#""event":"canceled""#));
+        let read = lines
+            .iter()
+            .map(|line| serde_json::from_str(line).map_err(|e| Error::JsonParse(e.to_string())))
+            .collect::<Result<Vec<EventRecord>>>()?;
+        assert_eq!(read, records);
+
+        fs::remove_dir_all(path.parent().unwrap()).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use std::future::Future;
+
+use crate::{
+    prelude::*, ClientCancelRequest, ClientOrderRequest, ExchangeClient, ExchangeResponseStatus,
+};
+
+/// Order entry surface used by strategies, so they can run against the real
+/// exchange as well as against mocks and simulated exchanges.
+pub trait ExchangeApi: Send + Sync {
+    fn bulk_order(
+        &self,
+        orders: Vec<ClientOrderRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + Send;
+
+    fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + Send;
+
+    fn update_leverage(
+        &self,
+        leverage: u32,
+        coin: &str,
+        is_cross: bool,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + Send;
+
+    fn sz_decimals(&self, coin: &str) -> Option<u32>;
+
+    /// Names of the base and quote tokens if `coin` is a spot pair.
+    fn spot_tokens(&self, coin: &str) -> Option<(String, String)>;
+
+    fn order(
+        &self,
+        order: ClientOrderRequest,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + Send {
+        self.bulk_order(vec![order])
+    }
+
+    fn cancel(
+        &self,
+        cancel: ClientCancelRequest,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + Send {
+        self.bulk_cancel(vec![cancel])
+    }
+}
+
+impl ExchangeApi for ExchangeClient {
+    async fn bulk_order(&self, orders: Vec<ClientOrderRequest>) -> Result<ExchangeResponseStatus> {
+        ExchangeClient::bulk_order(self, orders, None).await
+    }
+
+    async fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        ExchangeClient::bulk_cancel(self, cancels, None).await
+    }
+
+    async fn update_leverage(
+        &self,
+        leverage: u32,
+        coin: &str,
+        is_cross: bool,
+    ) -> Result<ExchangeResponseStatus> {
+        ExchangeClient::update_leverage(self, leverage, coin, is_cross, None).await
+    }
+
+    fn sz_decimals(&self, coin: &str) -> Option<u32> {
+        self.meta
+            .universe
+            .iter()
+            .find(|asset| asset.name == coin)
+            .map(|asset| asset.sz_decimals)
+            .or_else(|| {
+                self.spot_meta
+                    .tokens(coin)
+                    .map(|(base, _)| base.sz_decimals)
+            })
+    }
+
+    fn spot_tokens(&self, coin: &str) -> Option<(String, String)> {
+        self.spot_meta
+            .tokens(coin)
+            .map(|(base, quote)| (base.name.clone(), quote.name.clone()))
+    }
+}
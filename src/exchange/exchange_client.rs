@@ -3,18 +3,21 @@ use crate::{
     consts::MAINNET_API_URL,
     exchange::{
         actions::{
-            ApproveAgent, BulkCancel, BulkOrder, UpdateIsolatedMargin, UpdateLeverage, UsdSend,
+            ApproveAgent, BulkCancel, BulkOrder, CreateSubAccount, SpotSend, SubAccountTransfer,
+            UpdateIsolatedMargin, UpdateLeverage, UsdClassTransfer, UsdSend, VaultTransfer,
         },
         cancel::{CancelRequest, CancelRequestCloid},
         ClientCancelRequest, ClientOrderRequest,
     },
     helpers::{generate_random_key, next_nonce, uuid_to_hex_string},
     info::info_client::InfoClient,
-    meta::Meta,
+    meta::{Meta, SpotMeta},
     prelude::*,
     req::HttpClient,
     signature::sign_l1_action,
-    BaseUrl, BulkCancelCloid, Error, ExchangeResponseStatus,
+    ws::{PostRequest, WsManager},
+    ActionSigner, BaseUrl, BulkCancelCloid, CreateSubAccountResponseStatus, Error,
+    ExchangeResponseStatus, PostResponse,
 };
 use ethers::{
     abi::AbiEncode,
@@ -22,18 +25,28 @@ use ethers::{
     types::{Signature, H160, H256},
 };
 use gxhash::{HashMap, HashMapExt};
-use log::debug;
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tracing::{debug, warn};
 
 use super::cancel::ClientCancelRequestCloid;
 
 pub struct ExchangeClient {
     pub http_client: HttpClient,
-    pub wallet: LocalWallet,
+    pub signer: Arc<dyn ActionSigner>,
     pub meta: Meta,
+    pub spot_meta: SpotMeta,
     pub vault_address: Option<H160>,
     pub coin_to_asset: HashMap<String, u32>,
+    /// Connection that actions are posted over instead of HTTP requests,
+    /// once `use_websocket` was called.
+    ws_transport: Option<WsTransport>,
+}
+
+struct WsTransport {
+    ws_manager: WsManager,
+    timeout: Duration,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -56,6 +69,11 @@ pub enum Actions {
     Cancel(BulkCancel),
     CancelByCloid(BulkCancelCloid),
     ApproveAgent(ApproveAgent),
+    CreateSubAccount(CreateSubAccount),
+    SubAccountTransfer(SubAccountTransfer),
+    VaultTransfer(VaultTransfer),
+    SpotSend(SpotSend),
+    UsdClassTransfer(UsdClassTransfer),
 }
 
 impl Actions {
@@ -80,32 +98,82 @@ impl ExchangeClient {
         base_url: Option<BaseUrl>,
         meta: Option<Meta>,
         vault_address: Option<H160>,
+    ) -> Result<ExchangeClient> {
+        Self::with_signer(client, Arc::new(wallet), base_url, meta, vault_address).await
+    }
+
+    /// Creates a client that signs actions with `signer`, which may hold the
+    /// key outside of this process.
+    ///
+    /// The perp and spot metadata are fetched from the exchange unless
+    /// `meta` is given, in which case only the perps in `meta` can be traded
+    /// until `set_spot_meta` is called.
+    pub async fn with_signer(
+        client: Option<Client>,
+        signer: Arc<dyn ActionSigner>,
+        base_url: Option<BaseUrl>,
+        meta: Option<Meta>,
+        vault_address: Option<H160>,
     ) -> Result<ExchangeClient> {
         let client = client.unwrap_or_default();
         let base_url = base_url.unwrap_or(BaseUrl::Mainnet);
 
-        let meta = if let Some(meta) = meta {
-            meta
+        let (meta, spot_meta) = if let Some(meta) = meta {
+            (meta, SpotMeta::default())
         } else {
             let info = InfoClient::new(None, Some(base_url)).await?;
-            info.meta().await?
+            (info.meta().await?, info.spot_meta().await?)
         };
 
-        let mut coin_to_asset = HashMap::new();
-        for (asset_ind, asset) in meta.universe.iter().enumerate() {
-            coin_to_asset.insert(asset.name.clone(), asset_ind as u32);
-        }
-
-        Ok(ExchangeClient {
-            wallet,
+        let mut exchange_client = ExchangeClient {
+            signer,
             meta,
+            spot_meta: SpotMeta::default(),
             vault_address,
             http_client: HttpClient {
                 client,
                 base_url: base_url.get_url(),
             },
-            coin_to_asset,
-        })
+            coin_to_asset: HashMap::new(),
+            ws_transport: None,
+        };
+        exchange_client.set_spot_meta(spot_meta);
+        Ok(exchange_client)
+    }
+
+    /// Replaces the spot pairs that can be traded, keeping the perps.
+    pub fn set_spot_meta(&mut self, spot_meta: SpotMeta) {
+        let mut coin_to_asset = spot_meta.coin_to_asset();
+        for (asset_ind, asset) in self.meta.universe.iter().enumerate() {
+            coin_to_asset.insert(asset.name.clone(), asset_ind as u32);
+        }
+        self.coin_to_asset = coin_to_asset;
+        self.spot_meta = spot_meta;
+    }
+
+    /// Sends all further actions over a websocket connection with the `post`
+    /// method, which saves the overhead of an HTTP request per action.
+    /// Actions that can't be sent while the connection is down are sent as
+    /// HTTP requests instead. An action whose response doesn't arrive within
+    /// `timeout` fails with `Error::PostUnconfirmed`, since it may have been
+    /// executed anyway.
+    pub async fn use_websocket(&mut self, timeout: Duration) -> Result<()> {
+        let ws_manager =
+            WsManager::new(format!("ws{}/ws", &self.http_client.base_url[4..])).await?;
+        self.ws_transport = Some(WsTransport {
+            ws_manager,
+            timeout,
+        });
+        Ok(())
+    }
+
+    /// The wallet passed to a request, or the client's signer if there is
+    /// none.
+    fn signer<'a>(&'a self, wallet: Option<&'a LocalWallet>) -> &'a dyn ActionSigner {
+        match wallet {
+            Some(wallet) => wallet,
+            None => self.signer.as_ref(),
+        }
     }
 
     async fn post(
@@ -114,12 +182,52 @@ impl ExchangeClient {
         signature: Signature,
         nonce: u64,
     ) -> Result<ExchangeResponseStatus> {
+        self.post_for(action, signature, nonce, self.vault_address)
+            .await
+    }
+
+    /// Posts an action on behalf of `vault_address`, or of the signer's own
+    /// account if it's `None`.
+    async fn post_for<T: DeserializeOwned>(
+        &self,
+        action: serde_json::Value,
+        signature: Signature,
+        nonce: u64,
+        vault_address: Option<H160>,
+    ) -> Result<T> {
         let exchange_payload = ExchangePayload {
             action,
             signature,
             nonce,
-            vault_address: self.vault_address,
+            vault_address,
         };
+        if let Some(ws_transport) = &self.ws_transport {
+            let payload = serde_json::to_value(&exchange_payload)
+                .map_err(|e| Error::JsonParse(e.to_string()))?;
+            debug!("Posting request {payload}");
+            match ws_transport
+                .ws_manager
+                .post(PostRequest::Action(payload), ws_transport.timeout)
+                .await
+            {
+                Ok(PostResponse::Action(response)) => {
+                    return serde_json::from_value(response)
+                        .map_err(|e| Error::JsonParse(e.to_string()))
+                }
+                Ok(PostResponse::Error(error)) => return Err(Error::GenericRequest(error)),
+                Ok(PostResponse::Info(_)) => {
+                    return Err(Error::Websocket(
+                        "Received an info response to an action".to_string(),
+                    ))
+                }
+                // Not sent, so it's safe to send it again
+                Err(Error::Websocket(e)) => {
+                    warn!("Couldn't post over the websocket, sending an HTTP request: {e}");
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
         let res = serde_json::to_string(&exchange_payload)
             .map_err(|e| Error::JsonParse(e.to_string()))?;
         debug!("Sending request {res:?}");
@@ -140,7 +248,7 @@ impl ExchangeClient {
         destination: &str,
         wallet: Option<&LocalWallet>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let signer = self.signer(wallet);
         let hyperliquid_chain = if self.http_client.base_url.eq(MAINNET_API_URL) {
             "Mainnet".to_string()
         } else {
@@ -155,13 +263,81 @@ impl ExchangeClient {
             amount: amount.to_string(),
             time: timestamp,
         };
-        let signature = sign_typed_data(&usd_send, wallet)?;
+        let signature = sign_typed_data(&usd_send, signer).await?;
         let action = serde_json::to_value(Actions::UsdSend(usd_send))
             .map_err(|e| Error::JsonParse(e.to_string()))?;
 
         self.post(action, signature, timestamp).await
     }
 
+    /// Sends `amount` of the spot token `token`, identified as in
+    /// `TokenInfo::transfer_id`, to the spot account of `destination`.
+    pub async fn spot_transfer(
+        &self,
+        amount: &str,
+        destination: &str,
+        token: &str,
+        wallet: Option<&LocalWallet>,
+    ) -> Result<ExchangeResponseStatus> {
+        let signer = self.signer(wallet);
+        let hyperliquid_chain = if self.http_client.base_url.eq(MAINNET_API_URL) {
+            "Mainnet".to_string()
+        } else {
+            "Testnet".to_string()
+        };
+
+        let timestamp = next_nonce();
+        let spot_send = SpotSend {
+            signature_chain_id: 421614.into(),
+            hyperliquid_chain,
+            destination: destination.to_string(),
+            token: token.to_string(),
+            amount: amount.to_string(),
+            time: timestamp,
+        };
+        let signature = sign_typed_data(&spot_send, signer).await?;
+        let action = serde_json::to_value(Actions::SpotSend(spot_send))
+            .map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        self.post(action, signature, timestamp).await
+    }
+
+    /// Moves `amount` USDC from the spot to the perp balance if `to_perp`,
+    /// and back otherwise. Moves the balances of the vault or subaccount
+    /// the client trades for, if there is one.
+    pub async fn usd_class_transfer(
+        &self,
+        amount: f64,
+        to_perp: bool,
+        wallet: Option<&LocalWallet>,
+    ) -> Result<ExchangeResponseStatus> {
+        let signer = self.signer(wallet);
+        let hyperliquid_chain = if self.http_client.base_url.eq(MAINNET_API_URL) {
+            "Mainnet".to_string()
+        } else {
+            "Testnet".to_string()
+        };
+
+        let mut amount = amount.to_string();
+        if let Some(vault_address) = self.vault_address {
+            amount = format!("{amount} subaccount:{vault_address:?}");
+        }
+        let nonce = next_nonce();
+        let usd_class_transfer = UsdClassTransfer {
+            signature_chain_id: 421614.into(),
+            hyperliquid_chain,
+            amount,
+            to_perp,
+            nonce,
+        };
+        let signature = sign_typed_data(&usd_class_transfer, signer).await?;
+        let action = serde_json::to_value(Actions::UsdClassTransfer(usd_class_transfer))
+            .map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        // The vault is named in the amount instead
+        self.post_for(action, signature, nonce, None).await
+    }
+
     pub async fn order(
         &self,
         order: ClientOrderRequest,
@@ -175,7 +351,7 @@ impl ExchangeClient {
         orders: Vec<ClientOrderRequest>,
         wallet: Option<&LocalWallet>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let signer = self.signer(wallet);
         let timestamp = next_nonce();
 
         let mut transformed_orders = Vec::new();
@@ -192,7 +368,7 @@ impl ExchangeClient {
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
 
         let is_mainnet = self.http_client.base_url == BaseUrl::Mainnet.get_url();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = sign_l1_action(signer, connection_id, is_mainnet).await?;
         self.post(action, signature, timestamp).await
     }
 
@@ -209,7 +385,7 @@ impl ExchangeClient {
         cancels: Vec<ClientCancelRequest>,
         wallet: Option<&LocalWallet>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let signer = self.signer(wallet);
         let timestamp = next_nonce();
 
         let mut transformed_cancels = Vec::new();
@@ -231,7 +407,7 @@ impl ExchangeClient {
 
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
         let is_mainnet = self.http_client.base_url == BaseUrl::Mainnet.get_url();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = sign_l1_action(signer, connection_id, is_mainnet).await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -249,7 +425,7 @@ impl ExchangeClient {
         cancels: Vec<ClientCancelRequestCloid>,
         wallet: Option<&LocalWallet>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let signer = self.signer(wallet);
         let timestamp = next_nonce();
 
         let mut transformed_cancels: Vec<CancelRequestCloid> = Vec::new();
@@ -271,7 +447,7 @@ impl ExchangeClient {
         let connection_id = action.hash(timestamp, self.vault_address)?;
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
         let is_mainnet = self.http_client.base_url == BaseUrl::Mainnet.get_url();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = sign_l1_action(signer, connection_id, is_mainnet).await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -283,7 +459,7 @@ impl ExchangeClient {
         is_cross: bool,
         wallet: Option<&LocalWallet>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let signer = self.signer(wallet);
 
         let timestamp = next_nonce();
 
@@ -296,7 +472,7 @@ impl ExchangeClient {
         let connection_id = action.hash(timestamp, self.vault_address)?;
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
         let is_mainnet = self.http_client.base_url == BaseUrl::Mainnet.get_url();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = sign_l1_action(signer, connection_id, is_mainnet).await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -307,7 +483,7 @@ impl ExchangeClient {
         coin: &str,
         wallet: Option<&LocalWallet>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let signer = self.signer(wallet);
 
         let amount = (amount * 1_000_000.0).round() as i64;
         let timestamp = next_nonce();
@@ -321,22 +497,100 @@ impl ExchangeClient {
         let connection_id = action.hash(timestamp, self.vault_address)?;
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
         let is_mainnet = self.http_client.base_url == BaseUrl::Mainnet.get_url();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = sign_l1_action(signer, connection_id, is_mainnet).await?;
 
         self.post(action, signature, timestamp).await
     }
 
+    /// Creates a subaccount of the signer's account called `name`.
+    pub async fn create_sub_account(
+        &self,
+        name: &str,
+        wallet: Option<&LocalWallet>,
+    ) -> Result<CreateSubAccountResponseStatus> {
+        let action = Actions::CreateSubAccount(CreateSubAccount {
+            name: name.to_string(),
+        });
+        self.post_master_action(action, wallet).await
+    }
+
+    /// Moves `usd` USDC from the signer's account to the subaccount
+    /// `sub_account_user` if `is_deposit`, and back otherwise.
+    pub async fn sub_account_transfer(
+        &self,
+        sub_account_user: H160,
+        is_deposit: bool,
+        usd: f64,
+        wallet: Option<&LocalWallet>,
+    ) -> Result<ExchangeResponseStatus> {
+        let action = Actions::SubAccountTransfer(SubAccountTransfer {
+            sub_account_user,
+            is_deposit,
+            usd: (usd * 1_000_000.0).round() as u64,
+        });
+        self.post_master_action(action, wallet).await
+    }
+
+    /// Deposits `usd` USDC from the signer's account into the vault at
+    /// `vault_address` if `is_deposit`, and withdraws it otherwise.
+    pub async fn vault_transfer(
+        &self,
+        vault_address: H160,
+        is_deposit: bool,
+        usd: f64,
+        wallet: Option<&LocalWallet>,
+    ) -> Result<ExchangeResponseStatus> {
+        let action = Actions::VaultTransfer(VaultTransfer {
+            vault_address,
+            is_deposit,
+            usd: (usd * 1_000_000.0).round() as u64,
+        });
+        self.post_master_action(action, wallet).await
+    }
+
+    /// Signs and posts an action of the signer's own account, ignoring the
+    /// vault the client trades for.
+    async fn post_master_action<T: DeserializeOwned>(
+        &self,
+        action: Actions,
+        wallet: Option<&LocalWallet>,
+    ) -> Result<T> {
+        let signer = self.signer(wallet);
+        let timestamp = next_nonce();
+
+        let connection_id = action.hash(timestamp, None)?;
+        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
+        let is_mainnet = self.http_client.base_url == BaseUrl::Mainnet.get_url();
+        let signature = sign_l1_action(signer, connection_id, is_mainnet).await?;
+
+        self.post_for(action, signature, timestamp, None).await
+    }
+
     pub async fn approve_agent(
         &self,
         wallet: Option<&LocalWallet>,
     ) -> Result<(String, ExchangeResponseStatus)> {
-        let wallet = wallet.unwrap_or(&self.wallet);
         let key = H256::from(generate_random_key()?).encode_hex()[2..].to_string();
 
-        let address = key
+        let agent = key
             .parse::<LocalWallet>()
-            .map_err(|e| Error::PrivateKeyParse(e.to_string()))?
-            .address();
+            .map_err(|e| Error::PrivateKeyParse(e.to_string()))?;
+        let response = self
+            .approve_named_agent(Signer::address(&agent), None, wallet)
+            .await?;
+        Ok((key, response))
+    }
+
+    /// Approves `agent_address` to trade on behalf of the account. Approving
+    /// a new address under the name of an existing agent replaces it, while
+    /// an unnamed approval replaces the previous unnamed agent.
+    pub async fn approve_named_agent(
+        &self,
+        agent_address: H160,
+        agent_name: Option<&str>,
+        wallet: Option<&LocalWallet>,
+    ) -> Result<ExchangeResponseStatus> {
+        let signer = self.signer(wallet);
 
         let hyperliquid_chain = if self.http_client.base_url.eq(MAINNET_API_URL) {
             "Mainnet".to_string()
@@ -348,14 +602,14 @@ impl ExchangeClient {
         let approve_agent = ApproveAgent {
             signature_chain_id: 421614.into(),
             hyperliquid_chain,
-            agent_address: address,
-            agent_name: None,
+            agent_address,
+            agent_name: agent_name.map(ToString::to_string),
             nonce,
         };
-        let signature = sign_typed_data(&approve_agent, wallet)?;
+        let signature = sign_typed_data(&approve_agent, signer).await?;
         let action = serde_json::to_value(Actions::ApproveAgent(approve_agent))
             .map_err(|e| Error::JsonParse(e.to_string()))?;
-        Ok((key, self.post(action, signature, nonce).await?))
+        self.post(action, signature, nonce).await
     }
 }
 
@@ -366,7 +620,7 @@ mod tests {
     use super::*;
     use crate::{
         exchange::order::{Limit, OrderRequest, Trigger},
-        Order,
+        ExchangeApi, Order,
     };
 
     fn get_wallet() -> Result<LocalWallet> {
@@ -376,8 +630,8 @@ mod tests {
             .map_err(|e| Error::Wallet(e.to_string()))
     }
 
-    #[test]
-    fn test_limit_order_action_hashing() -> Result<()> {
+    #[tokio::test]
+    async fn test_limit_order_action_hashing() -> Result<()> {
         let wallet = get_wallet()?;
         let action = Actions::Order(BulkOrder {
             orders: vec![OrderRequest {
@@ -395,17 +649,17 @@ mod tests {
         });
         let connection_id = action.hash(1583838, None)?;
 
-        let signature = sign_l1_action(&wallet, connection_id, true)?;
+        let signature = sign_l1_action(&wallet, connection_id, true).await?;
         assert_eq!(signature.to_string(), "77957e58e70f43b6b68581f2dc42011fc384538a2e5b7bf42d5b936f19fbb67360721a8598727230f67080efee48c812a6a4442013fd3b0eed509171bef9f23f1c");
 
-        let signature = sign_l1_action(&wallet, connection_id, false)?;
+        let signature = sign_l1_action(&wallet, connection_id, false).await?;
         assert_eq!(signature.to_string(), "cd0925372ff1ed499e54883e9a6205ecfadec748f80ec463fe2f84f1209648776377961965cb7b12414186b1ea291e95fd512722427efcbcfb3b0b2bcd4d79d01c");
 
         Ok(())
     }
 
-    #[test]
-    fn test_limit_order_action_hashing_with_cloid() -> Result<()> {
+    #[tokio::test]
+    async fn test_limit_order_action_hashing_with_cloid() -> Result<()> {
         let cloid = uuid::Uuid::from_str("1e60610f-0b3d-4205-97c8-8c1fed2ad5ee")
             .map_err(|_e| uuid::Uuid::new_v4());
         let wallet = get_wallet()?;
@@ -425,17 +679,17 @@ mod tests {
         });
         let connection_id = action.hash(1583838, None)?;
 
-        let signature = sign_l1_action(&wallet, connection_id, true)?;
+        let signature = sign_l1_action(&wallet, connection_id, true).await?;
         assert_eq!(signature.to_string(), "d3e894092eb27098077145714630a77bbe3836120ee29df7d935d8510b03a08f456de5ec1be82aa65fc6ecda9ef928b0445e212517a98858cfaa251c4cd7552b1c");
 
-        let signature = sign_l1_action(&wallet, connection_id, false)?;
+        let signature = sign_l1_action(&wallet, connection_id, false).await?;
         assert_eq!(signature.to_string(), "3768349dbb22a7fd770fc9fc50c7b5124a7da342ea579b309f58002ceae49b4357badc7909770919c45d850aabb08474ff2b7b3204ae5b66d9f7375582981f111c");
 
         Ok(())
     }
 
-    #[test]
-    fn test_tpsl_order_action_hashing() -> Result<()> {
+    #[tokio::test]
+    async fn test_tpsl_order_action_hashing() -> Result<()> {
         for (tpsl, mainnet_signature, testnet_signature) in [
             (
                 "tp",
@@ -469,17 +723,17 @@ mod tests {
             });
             let connection_id = action.hash(1583838, None)?;
 
-            let signature = sign_l1_action(&wallet, connection_id, true)?;
+            let signature = sign_l1_action(&wallet, connection_id, true).await?;
             assert_eq!(signature.to_string(), mainnet_signature);
 
-            let signature = sign_l1_action(&wallet, connection_id, false)?;
+            let signature = sign_l1_action(&wallet, connection_id, false).await?;
             assert_eq!(signature.to_string(), testnet_signature);
         }
         Ok(())
     }
 
-    #[test]
-    fn test_cancel_action_hashing() -> Result<()> {
+    #[tokio::test]
+    async fn test_cancel_action_hashing() -> Result<()> {
         let wallet = get_wallet()?;
         let action = Actions::Cancel(BulkCancel {
             cancels: vec![CancelRequest {
@@ -489,12 +743,127 @@ mod tests {
         });
         let connection_id = action.hash(1583838, None)?;
 
-        let signature = sign_l1_action(&wallet, connection_id, true)?;
+        let signature = sign_l1_action(&wallet, connection_id, true).await?;
         assert_eq!(signature.to_string(), "02f76cc5b16e0810152fa0e14e7b219f49c361e3325f771544c6f54e157bf9fa17ed0afc11a98596be85d5cd9f86600aad515337318f7ab346e5ccc1b03425d51b");
 
-        let signature = sign_l1_action(&wallet, connection_id, false)?;
+        let signature = sign_l1_action(&wallet, connection_id, false).await?;
         assert_eq!(signature.to_string(), "6ffebadfd48067663390962539fbde76cfa36f53be65abe2ab72c9db6d0db44457720db9d7c4860f142a484f070c84eb4b9694c3a617c83f0d698a27e55fd5e01c");
 
         Ok(())
     }
+
+    #[test]
+    fn test_sub_account_actions_serialization() -> Result<()> {
+        let sub_account_user = H160::from_str("0x1d9470d4b963f552e6f671a81619d395877bf409")
+            .map_err(|e| Error::GenericParse(e.to_string()))?;
+        let action = Actions::SubAccountTransfer(SubAccountTransfer {
+            sub_account_user,
+            is_deposit: true,
+            usd: 1_000_000,
+        });
+        assert_eq!(
+            serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?,
+            serde_json::json!({
+                "type": "subAccountTransfer",
+                "subAccountUser": "0x1d9470d4b963f552e6f671a81619d395877bf409",
+                "isDeposit": true,
+                "usd": 1_000_000,
+            })
+        );
+
+        let action = Actions::CreateSubAccount(CreateSubAccount {
+            name: "market_maker".to_string(),
+        });
+        assert_eq!(
+            serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?,
+            serde_json::json!({"type": "createSubAccount", "name": "market_maker"})
+        );
+
+        let response: CreateSubAccountResponseStatus = serde_json::from_str(
+            r#"{"status":"ok","response":{"type":"createSubAccount","data":"0x1d9470d4b963f552e6f671a81619d395877bf409"}}"#,
+        )
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        assert!(matches!(
+            response,
+            CreateSubAccountResponseStatus::Ok(response) if response.data == sub_account_user
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_spot_actions_serialization() -> Result<()> {
+        let action = Actions::SpotSend(SpotSend {
+            signature_chain_id: 421614.into(),
+            hyperliquid_chain: "Testnet".to_string(),
+            destination: "0x0D1d9635D0640821d15e323ac8AdADfA9c111414".to_string(),
+            token: "PURR:0xc4bf3f870c0e9465323c0b6ed28096c2".to_string(),
+            amount: "1".to_string(),
+            time: 1690393044548,
+        });
+        assert_eq!(
+            serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?,
+            serde_json::json!({
+                "type": "spotSend",
+                "signatureChainId": "0x66eee",
+                "hyperliquidChain": "Testnet",
+                "destination": "0x0D1d9635D0640821d15e323ac8AdADfA9c111414",
+                "token": "PURR:0xc4bf3f870c0e9465323c0b6ed28096c2",
+                "amount": "1",
+                "time": 1690393044548u64,
+            })
+        );
+
+        let action = Actions::UsdClassTransfer(UsdClassTransfer {
+            signature_chain_id: 421614.into(),
+            hyperliquid_chain: "Testnet".to_string(),
+            amount: "10.5".to_string(),
+            to_perp: true,
+            nonce: 1690393044548,
+        });
+        assert_eq!(
+            serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?,
+            serde_json::json!({
+                "type": "usdClassTransfer",
+                "signatureChainId": "0x66eee",
+                "hyperliquidChain": "Testnet",
+                "amount": "10.5",
+                "toPerp": true,
+                "nonce": 1690393044548u64,
+            })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spot_assets_follow_perps() -> Result<()> {
+        let meta = serde_json::from_str(
+            r#"{"universe":[{"name":"BTC","szDecimals":5},{"name":"ETH","szDecimals":4}]}"#,
+        )
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let spot_meta = serde_json::from_str(
+            r#"{
+                "universe":[{"tokens":[1,0],"name":"@3","index":3,"isCanonical":false}],
+                "tokens":[
+                    {"name":"USDC","szDecimals":8,"weiDecimals":8,"index":0,"tokenId":"0x6d1e7cde53ba9467b783cb7c530ce054","isCanonical":true},
+                    {"name":"HYPE","szDecimals":2,"weiDecimals":8,"index":1,"tokenId":"0x0d01dc56dcaaca66ad901c959b4011ec","isCanonical":false}
+                ]
+            }"#,
+        )
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let mut client = ExchangeClient::new(None, get_wallet()?, None, Some(meta), None).await?;
+        assert!(!client.coin_to_asset.contains_key("@3"));
+        client.set_spot_meta(spot_meta);
+
+        assert_eq!(client.coin_to_asset.get("ETH"), Some(&1));
+        assert_eq!(client.coin_to_asset.get("@3"), Some(&10_003));
+        assert_eq!(client.coin_to_asset.get("HYPE/USDC"), Some(&10_003));
+        assert_eq!(client.sz_decimals("@3"), Some(2));
+        assert_eq!(
+            client.spot_tokens("@3"),
+            Some(("HYPE".to_string(), "USDC".to_string()))
+        );
+        assert_eq!(client.spot_tokens("ETH"), None);
+        Ok(())
+    }
 }
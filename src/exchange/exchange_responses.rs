@@ -1,3 +1,4 @@
+use ethers::types::H160;
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug, Clone)]
@@ -43,3 +44,19 @@ pub enum ExchangeResponseStatus {
     Ok(ExchangeResponse),
     Err(String),
 }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateSubAccountResponse {
+    #[serde(rename = "type")]
+    pub response_type: String,
+    /// Address of the new subaccount.
+    pub data: H160,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "status", content = "response")]
+pub enum CreateSubAccountResponseStatus {
+    Ok(CreateSubAccountResponse),
+    Err(String),
+}
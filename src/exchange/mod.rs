@@ -1,11 +1,13 @@
 mod actions;
 mod cancel;
+mod exchange_api;
 mod exchange_client;
 mod exchange_responses;
 mod order;
 
 pub use actions::*;
 pub use cancel::{ClientCancelRequest, ClientCancelRequestCloid};
+pub use exchange_api::ExchangeApi;
 pub use exchange_client::*;
 pub use exchange_responses::*;
 pub use order::{ClientLimit, ClientOrder, ClientOrderRequest, ClientTrigger, Order};
@@ -1,14 +1,15 @@
 use crate::{consts::*, prelude::*, Error};
 use chrono::prelude::Utc;
 use lazy_static::lazy_static;
-use log::info;
 use rand::{thread_rng, Rng};
+use serde::Deserialize;
 use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::info;
 use uuid::Uuid;
 
-fn now_timestamp_ms() -> u64 {
-    let now = Utc::now();
-    now.timestamp_millis() as u64
+/// Milliseconds since the Unix epoch.
+pub fn now_timestamp_ms() -> u64 {
+    Utc::now().timestamp_millis().try_into().unwrap_or_default()
 }
 
 pub(crate) fn next_nonce() -> u64 {
@@ -76,7 +77,8 @@ pub fn bps_diff(x: f64, y: f64) -> u16 {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BaseUrl {
     Localhost,
     Testnet,
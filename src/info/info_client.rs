@@ -1,20 +1,23 @@
 use crate::{
     info::{
-        CandlesSnapshotResponse, FundingHistoryResponse, L2SnapshotResponse, OpenOrdersResponse,
-        RecentTradesResponse, UserFillsResponse, UserStateResponse,
+        pagination::{paginate, PaginationConfig},
+        CandlesSnapshotResponse, FrontendOpenOrdersResponse, FundingHistoryResponse,
+        HistoricalOrdersResponse, L2SnapshotResponse, OpenOrdersResponse, RecentTradesResponse,
+        SpotUserStateResponse, UserFeesResponse, UserFillsResponse, UserFundingResponse,
+        UserNonFundingLedgerUpdatesResponse, UserRateLimitResponse, UserStateResponse,
     },
-    meta::Meta,
+    meta::{AssetCtx, Meta, SpotAssetCtx, SpotMeta},
     prelude::*,
     req::HttpClient,
-    ws::{Subscription, WsManager},
-    BaseUrl, Error, Message,
+    ws::{ConnectionKey, Subscription, WsManager},
+    BaseUrl, Error, SubscriptionSender,
 };
 
 use ethers::types::H160;
+use futures_util::Stream;
 use gxhash::HashMap;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc::UnboundedSender;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -37,22 +40,64 @@ pub enum InfoRequest {
     UserStates {
         users: Vec<H160>,
     },
+    #[serde(rename = "spotClearinghouseState")]
+    SpotUserState {
+        user: H160,
+    },
     OpenOrders {
         user: H160,
     },
+    FrontendOpenOrders {
+        user: H160,
+    },
+    HistoricalOrders {
+        user: H160,
+    },
+    UserFees {
+        user: H160,
+    },
+    UserRateLimit {
+        user: H160,
+    },
+    #[serde(rename_all = "camelCase")]
+    UserFunding {
+        user: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    UserNonFundingLedgerUpdates {
+        user: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    },
     Meta,
+    MetaAndAssetCtxs,
+    SpotMeta,
+    SpotMetaAndAssetCtxs,
     AllMids,
     UserFills {
         user: H160,
     },
     #[serde(rename_all = "camelCase")]
+    UserFillsByTime {
+        user: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
     FundingHistory {
         coin: String,
         start_time: u64,
         end_time: Option<u64>,
     },
+    #[serde(rename_all = "camelCase")]
     L2Book {
         coin: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        n_sig_figs: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mantissa: Option<u32>,
     },
     RecentTrades {
         coin: String,
@@ -66,6 +111,10 @@ pub enum InfoRequest {
 pub struct InfoClient {
     pub http_client: HttpClient,
     pub(crate) ws_manager: Option<WsManager>,
+    /// Connections of the subscriptions that need one of their own.
+    dedicated_ws_managers: HashMap<ConnectionKey, WsManager>,
+    /// Pacing of the paginated fetchers.
+    pub pagination: PaginationConfig,
 }
 
 impl InfoClient {
@@ -76,42 +125,71 @@ impl InfoClient {
         Ok(InfoClient {
             http_client: HttpClient { client, base_url },
             ws_manager: None,
+            dedicated_ws_managers: HashMap::default(),
+            pagination: PaginationConfig::default(),
         })
     }
 
+    fn ws_url(&self) -> String {
+        format!("ws{}/ws", &self.http_client.base_url[4..])
+    }
+
+    /// Subscribes to `subscription`, sending its messages to `sender_channel`.
+    /// The user events, order updates and notifications of each user, and
+    /// each aggregation of a book other than the default, are received on a
+    /// connection of their own, so that their messages can be told apart.
     pub async fn subscribe(
         &mut self,
         subscription: Subscription,
-        sender_channel: UnboundedSender<Message>,
+        sender_channel: impl Into<SubscriptionSender>,
     ) -> Result<u32> {
-        if self.ws_manager.is_none() {
-            let ws_manager =
-                WsManager::new(format!("ws{}/ws", &self.http_client.base_url[4..])).await?;
-            self.ws_manager = Some(ws_manager);
-        }
-
         let identifier =
             serde_json::to_string(&subscription).map_err(|e| Error::JsonParse(e.to_string()))?;
 
-        self.ws_manager
-            .as_mut()
+        let ws_manager = if let Some(connection_key) = subscription.connection_key() {
+            if !self.dedicated_ws_managers.contains_key(&connection_key) {
+                let ws_manager = WsManager::new(self.ws_url()).await?;
+                self.dedicated_ws_managers
+                    .insert(connection_key.clone(), ws_manager);
+            }
+            self.dedicated_ws_managers.get_mut(&connection_key)
+        } else {
+            if self.ws_manager.is_none() {
+                self.ws_manager = Some(WsManager::new(self.ws_url()).await?);
+            }
+            self.ws_manager.as_mut()
+        };
+        ws_manager
             .ok_or(Error::WsManagerNotFound)?
-            .add_subscription(identifier, sender_channel)
+            .add_subscription(identifier, sender_channel.into())
             .await
     }
 
     pub async fn unsubscribe(&mut self, subscription_id: u32) -> Result<()> {
-        if self.ws_manager.is_none() {
-            let ws_manager =
-                WsManager::new(format!("ws{}/ws", &self.http_client.base_url[4..])).await?;
-            self.ws_manager = Some(ws_manager);
-        }
+        let connection_key = self
+            .dedicated_ws_managers
+            .iter()
+            .find(|(_, ws_manager)| ws_manager.has_subscription(subscription_id))
+            .map(|(connection_key, _)| connection_key.clone());
+        let Some(connection_key) = connection_key else {
+            return self
+                .ws_manager
+                .as_mut()
+                .ok_or(Error::SubscriptionNotFound)?
+                .remove_subscription(subscription_id)
+                .await;
+        };
 
-        self.ws_manager
-            .as_mut()
-            .ok_or(Error::WsManagerNotFound)?
-            .remove_subscription(subscription_id)
-            .await
+        let ws_manager = self
+            .dedicated_ws_managers
+            .get_mut(&connection_key)
+            .ok_or(Error::SubscriptionNotFound)?;
+        ws_manager.remove_subscription(subscription_id).await?;
+        // Close the connection once it has no subscriptions left
+        if ws_manager.is_empty() {
+            self.dedicated_ws_managers.remove(&connection_key);
+        }
+        Ok(())
     }
 
     pub async fn open_orders(&self, address: H160) -> Result<Vec<OpenOrdersResponse>> {
@@ -122,6 +200,116 @@ impl InfoClient {
         serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
     }
 
+    /// Open orders of `address`, including trigger orders and the TP/SL
+    /// orders attached to them.
+    pub async fn frontend_open_orders(
+        &self,
+        address: H160,
+    ) -> Result<Vec<FrontendOpenOrdersResponse>> {
+        let input = InfoRequest::FrontendOpenOrders { user: address };
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    /// The most recent orders of `address` with their latest status.
+    pub async fn historical_orders(&self, address: H160) -> Result<Vec<HistoricalOrdersResponse>> {
+        let input = InfoRequest::HistoricalOrders { user: address };
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    pub async fn user_fees(&self, address: H160) -> Result<UserFeesResponse> {
+        let input = InfoRequest::UserFees { user: address };
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    pub async fn user_rate_limit(&self, address: H160) -> Result<UserRateLimitResponse> {
+        let input = InfoRequest::UserRateLimit { user: address };
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    /// Funding payments of the positions of `address` between `start_time`
+    /// and `end_time`, or now, capped to one page.
+    pub async fn user_funding(
+        &self,
+        address: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    ) -> Result<Vec<UserFundingResponse>> {
+        let input = InfoRequest::UserFunding {
+            user: address,
+            start_time,
+            end_time,
+        };
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    /// All funding payments of `address` between `start_time` and
+    /// `end_time`, fetched page by page with `user_funding`.
+    pub fn user_funding_stream(
+        &self,
+        address: H160,
+        start_time: u64,
+        end_time: u64,
+    ) -> impl Stream<Item = Result<UserFundingResponse>> + '_ {
+        paginate(
+            self.pagination,
+            start_time,
+            end_time,
+            move |start_time, end_time| self.user_funding(address, start_time, Some(end_time)),
+        )
+    }
+
+    /// Deposits, withdrawals, transfers and liquidations of `address`
+    /// between `start_time` and `end_time`, or now, capped to one page.
+    pub async fn user_non_funding_ledger_updates(
+        &self,
+        address: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    ) -> Result<Vec<UserNonFundingLedgerUpdatesResponse>> {
+        let input = InfoRequest::UserNonFundingLedgerUpdates {
+            user: address,
+            start_time,
+            end_time,
+        };
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    /// All ledger updates of `address` between `start_time` and `end_time`,
+    /// fetched page by page with `user_non_funding_ledger_updates`.
+    pub fn user_non_funding_ledger_updates_stream(
+        &self,
+        address: H160,
+        start_time: u64,
+        end_time: u64,
+    ) -> impl Stream<Item = Result<UserNonFundingLedgerUpdatesResponse>> + '_ {
+        paginate(
+            self.pagination,
+            start_time,
+            end_time,
+            move |start_time, end_time| {
+                self.user_non_funding_ledger_updates(address, start_time, Some(end_time))
+            },
+        )
+    }
+
     pub async fn user_state(&self, address: H160) -> Result<UserStateResponse> {
         let input = InfoRequest::UserState { user: address };
         let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
@@ -138,6 +326,33 @@ impl InfoClient {
         serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
     }
 
+    /// Token balances of the spot account of `address`.
+    pub async fn spot_user_state(&self, address: H160) -> Result<SpotUserStateResponse> {
+        let input = InfoRequest::SpotUserState { user: address };
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    pub async fn spot_meta(&self) -> Result<SpotMeta> {
+        let input = InfoRequest::SpotMeta;
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    /// The spot pairs and tokens, with the market data of each pair in the
+    /// order of `SpotMeta::universe`.
+    pub async fn spot_meta_and_asset_ctxs(&self) -> Result<(SpotMeta, Vec<SpotAssetCtx>)> {
+        let input = InfoRequest::SpotMetaAndAssetCtxs;
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
     pub async fn meta(&self) -> Result<Meta> {
         let input = InfoRequest::Meta;
         let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
@@ -146,6 +361,16 @@ impl InfoClient {
         serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
     }
 
+    /// The perps, with the market data of each perp in the order of
+    /// `Meta::universe`.
+    pub async fn meta_and_asset_ctxs(&self) -> Result<(Meta, Vec<AssetCtx>)> {
+        let input = InfoRequest::MetaAndAssetCtxs;
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
     pub async fn all_mids(&self) -> Result<HashMap<String, String>> {
         let input = InfoRequest::AllMids;
         let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
@@ -162,6 +387,43 @@ impl InfoClient {
         serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
     }
 
+    /// Fills of `address` between `start_time` and `end_time`, or now,
+    /// capped to one page.
+    pub async fn user_fills_by_time(
+        &self,
+        address: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    ) -> Result<Vec<UserFillsResponse>> {
+        let input = InfoRequest::UserFillsByTime {
+            user: address,
+            start_time,
+            end_time,
+        };
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    /// All fills of `address` between `start_time` and `end_time`, fetched
+    /// page by page with `user_fills_by_time`.
+    pub fn user_fills_stream(
+        &self,
+        address: H160,
+        start_time: u64,
+        end_time: u64,
+    ) -> impl Stream<Item = Result<UserFillsResponse>> + '_ {
+        paginate(
+            self.pagination,
+            start_time,
+            end_time,
+            move |start_time, end_time| {
+                self.user_fills_by_time(address, start_time, Some(end_time))
+            },
+        )
+    }
+
     pub async fn funding_history(
         &self,
         coin: String,
@@ -179,6 +441,24 @@ impl InfoClient {
         serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
     }
 
+    /// All funding rates of `coin` between `start_time` and `end_time`,
+    /// fetched page by page with `funding_history`.
+    pub fn funding_history_stream(
+        &self,
+        coin: String,
+        start_time: u64,
+        end_time: u64,
+    ) -> impl Stream<Item = Result<FundingHistoryResponse>> + '_ {
+        paginate(
+            self.pagination,
+            start_time,
+            end_time,
+            move |start_time, end_time| {
+                self.funding_history(coin.clone(), start_time, Some(end_time))
+            },
+        )
+    }
+
     pub async fn recent_trades(&self, coin: String) -> Result<Vec<RecentTradesResponse>> {
         let input = InfoRequest::RecentTrades { coin };
         let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
@@ -188,7 +468,23 @@ impl InfoClient {
     }
 
     pub async fn l2_snapshot(&self, coin: String) -> Result<L2SnapshotResponse> {
-        let input = InfoRequest::L2Book { coin };
+        self.l2_snapshot_aggregated(coin, None, None).await
+    }
+
+    /// Book of `coin` with prices aggregated to `n_sig_figs` significant
+    /// figures, from 2 to 5, and, with 5 significant figures, the last one
+    /// rounded to a multiple of `mantissa`, which is 2 or 5.
+    pub async fn l2_snapshot_aggregated(
+        &self,
+        coin: String,
+        n_sig_figs: Option<u32>,
+        mantissa: Option<u32>,
+    ) -> Result<L2SnapshotResponse> {
+        let input = InfoRequest::L2Book {
+            coin,
+            n_sig_figs,
+            mantissa,
+        };
         let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
 
         let return_data = self.http_client.post("/info", data).await?;
@@ -215,4 +511,23 @@ impl InfoClient {
         let return_data = self.http_client.post("/info", data).await?;
         serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
     }
+
+    /// All candles of `coin` opened between `start_time` and `end_time`,
+    /// fetched page by page with `candles_snapshot`.
+    pub fn candles_stream(
+        &self,
+        coin: String,
+        interval: String,
+        start_time: u64,
+        end_time: u64,
+    ) -> impl Stream<Item = Result<CandlesSnapshotResponse>> + '_ {
+        paginate(
+            self.pagination,
+            start_time,
+            end_time,
+            move |start_time, end_time| {
+                self.candles_snapshot(coin.clone(), interval.clone(), start_time, end_time)
+            },
+        )
+    }
 }
@@ -0,0 +1,74 @@
+use std::future::Future;
+
+use ethers::types::H160;
+
+use crate::{
+    prelude::*, FundingHistoryResponse, InfoClient, OpenOrdersResponse, SpotUserStateResponse,
+    Subscription, SubscriptionSender, UserStateResponse,
+};
+
+/// Account queries used by strategies, so they can run against the real
+/// exchange as well as against mocks and simulated exchanges.
+pub trait MarketDataApi: Send + Sync {
+    fn open_orders(
+        &self,
+        address: H160,
+    ) -> impl Future<Output = Result<Vec<OpenOrdersResponse>>> + Send;
+
+    fn user_state(&self, address: H160) -> impl Future<Output = Result<UserStateResponse>> + Send;
+
+    fn spot_user_state(
+        &self,
+        address: H160,
+    ) -> impl Future<Output = Result<SpotUserStateResponse>> + Send;
+
+    fn funding_history(
+        &self,
+        coin: String,
+        start_time: u64,
+        end_time: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<FundingHistoryResponse>>> + Send;
+
+    fn subscribe(
+        &mut self,
+        subscription: Subscription,
+        sender_channel: SubscriptionSender,
+    ) -> impl Future<Output = Result<u32>> + Send;
+
+    fn unsubscribe(&mut self, subscription_id: u32) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl MarketDataApi for InfoClient {
+    async fn open_orders(&self, address: H160) -> Result<Vec<OpenOrdersResponse>> {
+        InfoClient::open_orders(self, address).await
+    }
+
+    async fn user_state(&self, address: H160) -> Result<UserStateResponse> {
+        InfoClient::user_state(self, address).await
+    }
+
+    async fn spot_user_state(&self, address: H160) -> Result<SpotUserStateResponse> {
+        InfoClient::spot_user_state(self, address).await
+    }
+
+    async fn funding_history(
+        &self,
+        coin: String,
+        start_time: u64,
+        end_time: Option<u64>,
+    ) -> Result<Vec<FundingHistoryResponse>> {
+        InfoClient::funding_history(self, coin, start_time, end_time).await
+    }
+
+    async fn subscribe(
+        &mut self,
+        subscription: Subscription,
+        sender_channel: SubscriptionSender,
+    ) -> Result<u32> {
+        InfoClient::subscribe(self, subscription, sender_channel).await
+    }
+
+    async fn unsubscribe(&mut self, subscription_id: u32) -> Result<()> {
+        InfoClient::unsubscribe(self, subscription_id).await
+    }
+}
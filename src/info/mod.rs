@@ -1,6 +1,10 @@
 pub(super) mod info_client;
+mod market_data_api;
+mod pagination;
 mod response_structs;
 mod sub_structs;
 
+pub use market_data_api::MarketDataApi;
+pub use pagination::PaginationConfig;
 pub use response_structs::*;
 pub use sub_structs::*;
@@ -0,0 +1,321 @@
+use std::{collections::HashSet, future::Future, hash::Hash, time::Duration};
+
+use futures_util::{stream, Stream, TryStreamExt};
+use tokio::time::sleep;
+use tracing::warn;
+
+use crate::{
+    info::{
+        CandlesSnapshotResponse, FundingHistoryResponse, UserFillsResponse, UserFundingResponse,
+        UserNonFundingLedgerUpdatesResponse,
+    },
+    prelude::*,
+    Error,
+};
+
+const TOO_MANY_REQUESTS: u16 = 429;
+
+/// How the paginated fetchers of `InfoClient` pace their requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaginationConfig {
+    /// Pause between two pages.
+    pub page_delay: Duration,
+    /// How often a page is retried after the request was rate limited.
+    pub max_retries: u32,
+    /// Pause before the first retry, doubled for every further retry.
+    pub retry_delay: Duration,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self {
+            page_delay: Duration::from_millis(500),
+            max_retries: 5,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// An item of a time ordered response, which is fetched page by page.
+pub(crate) trait Paged {
+    /// Tells apart items with the same time.
+    type Key: Eq + Hash;
+
+    /// Most items returned per request.
+    const PAGE_LIMIT: usize;
+
+    fn time(&self) -> u64;
+
+    fn key(&self) -> Self::Key;
+}
+
+impl Paged for CandlesSnapshotResponse {
+    type Key = u64;
+
+    const PAGE_LIMIT: usize = 5000;
+
+    fn time(&self) -> u64 {
+        self.time_open
+    }
+
+    fn key(&self) -> u64 {
+        self.time_open
+    }
+}
+
+impl Paged for FundingHistoryResponse {
+    type Key = u64;
+
+    const PAGE_LIMIT: usize = 500;
+
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn key(&self) -> u64 {
+        self.time
+    }
+}
+
+impl Paged for UserFillsResponse {
+    type Key = (String, u64, u64);
+
+    const PAGE_LIMIT: usize = 2000;
+
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn key(&self) -> Self::Key {
+        (self.hash.clone(), self.oid, self.tid)
+    }
+}
+
+impl Paged for UserFundingResponse {
+    type Key = String;
+
+    const PAGE_LIMIT: usize = 500;
+
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn key(&self) -> String {
+        self.delta.coin.clone()
+    }
+}
+
+impl Paged for UserNonFundingLedgerUpdatesResponse {
+    type Key = String;
+
+    const PAGE_LIMIT: usize = 500;
+
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn key(&self) -> String {
+        self.hash.clone()
+    }
+}
+
+struct PageState<F, K> {
+    fetch: F,
+    start_time: u64,
+    /// Keys of the items returned so far with a time of `start_time`.
+    boundary: HashSet<K>,
+    first: bool,
+}
+
+/// Fetches one page, retrying with a growing delay while rate limited.
+async fn fetch_page<T, F, Fut>(
+    config: PaginationConfig,
+    fetch: &mut F,
+    start_time: u64,
+    end_time: u64,
+) -> Result<Vec<T>>
+where
+    F: FnMut(u64, u64) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    let mut retry_delay = config.retry_delay;
+    let mut retries = 0;
+    loop {
+        match fetch(start_time, end_time).await {
+            Err(Error::ClientRequest { status_code, .. })
+                if status_code == TOO_MANY_REQUESTS && retries < config.max_retries =>
+            {
+                warn!("Rate limited fetching page from {start_time}, retrying in {retry_delay:?}");
+                sleep(retry_delay).await;
+                retry_delay *= 2;
+                retries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Streams the items between `start_time` and `end_time`, in order of their
+/// time, by fetching page after page with `fetch`.
+///
+/// Each page starts at the latest time of the previous one, since more items
+/// may share that time than fit the page, and the items already returned are
+/// skipped. The stream ends with the first page that adds nothing new, or
+/// fails if that page is full, since more items share its time than a page
+/// holds and the rest can't be fetched.
+pub(crate) fn paginate<'a, T, F, Fut>(
+    config: PaginationConfig,
+    start_time: u64,
+    end_time: u64,
+    fetch: F,
+) -> impl Stream<Item = Result<T>> + 'a
+where
+    T: Paged + 'a,
+    T::Key: 'a,
+    F: FnMut(u64, u64) -> Fut + 'a,
+    Fut: Future<Output = Result<Vec<T>>> + 'a,
+{
+    let state = PageState {
+        fetch,
+        start_time,
+        boundary: HashSet::new(),
+        first: true,
+    };
+    stream::try_unfold(state, move |mut state| async move {
+        if state.start_time > end_time {
+            return Ok(None);
+        }
+        if !state.first {
+            sleep(config.page_delay).await;
+        }
+        state.first = false;
+
+        let mut page = fetch_page(config, &mut state.fetch, state.start_time, end_time).await?;
+        let fetched = page.len();
+        page.sort_by_key(Paged::time);
+        page.retain(|item| {
+            item.time() > state.start_time
+                || (item.time() == state.start_time && !state.boundary.contains(&item.key()))
+        });
+        let Some(last_time) = page.last().map(Paged::time) else {
+            if fetched >= T::PAGE_LIMIT {
+                return Err(Error::GenericRequest(format!(
+                    "More than {} items at time {}, the rest of them can't be fetched",
+                    T::PAGE_LIMIT,
+                    state.start_time
+                )));
+            }
+            return Ok(None);
+        };
+
+        if last_time > state.start_time {
+            state.start_time = last_time;
+            state.boundary.clear();
+        }
+        state.boundary.extend(
+            page.iter()
+                .filter(|item| item.time() == last_time)
+                .map(Paged::key),
+        );
+        Ok(Some((stream::iter(page.into_iter().map(Ok)), state)))
+    })
+    .try_flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use reqwest::header::HeaderMap;
+
+    use super::*;
+
+    fn funding(time: u64) -> FundingHistoryResponse {
+        FundingHistoryResponse {
+            coin: "ETH".to_string(),
+            funding_rate: "0.0001".to_string(),
+            premium: "0.0".to_string(),
+            time,
+        }
+    }
+
+    fn config() -> PaginationConfig {
+        PaginationConfig {
+            page_delay: Duration::ZERO,
+            max_retries: 1,
+            retry_delay: Duration::ZERO,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pages_are_joined_without_duplicates() -> Result<()> {
+        // Pages of at most 3 items, each starting with the last of the
+        // previous page
+        let history = [1, 2, 3, 4, 5, 6, 7].map(funding);
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        let requested = requests.clone();
+        let fetched = paginate(config(), 2, 6, move |start_time, end_time| {
+            requested.borrow_mut().push(start_time);
+            let page = history
+                .iter()
+                .filter(|entry| (start_time..=end_time).contains(&entry.time))
+                .take(3)
+                .map(|entry| funding(entry.time))
+                .collect::<Vec<_>>();
+            async move { Ok(page) }
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        let times = fetched.iter().map(|entry| entry.time).collect::<Vec<_>>();
+        assert_eq!(times, vec![2, 3, 4, 5, 6]);
+        assert_eq!(*requests.borrow(), vec![2, 4, 6]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_full_page_at_one_time_fails() {
+        let limit = FundingHistoryResponse::PAGE_LIMIT;
+        let fetched = paginate(config(), 1, 10, move |start_time, _| {
+            // Full pages of items at time 2 only, followed by an item at
+            // time 3 that never fits a page
+            let page = (0..limit)
+                .map(|_| funding(start_time.max(2)))
+                .collect::<Vec<_>>();
+            async move { Ok(page) }
+        })
+        .try_collect::<Vec<_>>()
+        .await;
+
+        assert!(matches!(fetched, Err(Error::GenericRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_pages_are_retried() {
+        let attempts = Rc::new(RefCell::new(0));
+        let attempted = attempts.clone();
+        let fetched = paginate(config(), 0, 10, move |_, _| {
+            *attempted.borrow_mut() += 1;
+            async {
+                Err::<Vec<FundingHistoryResponse>, _>(Error::ClientRequest {
+                    status_code: TOO_MANY_REQUESTS,
+                    error_code: None,
+                    error_message: "rate limited".to_string(),
+                    headers: Box::new(HeaderMap::new()),
+                    error_data: None,
+                })
+            }
+        })
+        .try_collect::<Vec<_>>()
+        .await;
+
+        assert!(matches!(
+            fetched,
+            Err(Error::ClientRequest {
+                status_code: TOO_MANY_REQUESTS,
+                ..
+            })
+        ));
+        assert_eq!(*attempts.borrow(), 2);
+    }
+}
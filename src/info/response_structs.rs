@@ -1,4 +1,6 @@
-use crate::info::{AssetPosition, Level, MarginSummary};
+use crate::info::{
+    AssetPosition, DailyUserVlm, FundingDelta, LedgerUpdate, Level, MarginSummary, UserTokenBalance,
+};
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
@@ -10,6 +12,11 @@ pub struct UserStateResponse {
     pub withdrawable: String,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct SpotUserStateResponse {
+    pub balances: Vec<UserTokenBalance>,
+}
+
 #[derive(serde::Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct OpenOrdersResponse {
@@ -21,6 +28,75 @@ pub struct OpenOrdersResponse {
     pub timestamp: u64,
 }
 
+/// An open order with its trigger and TP/SL details, as shown in the
+/// frontend.
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontendOpenOrdersResponse {
+    pub coin: String,
+    pub side: String,
+    pub limit_px: String,
+    pub sz: String,
+    pub oid: u64,
+    pub timestamp: u64,
+    pub orig_sz: String,
+    pub order_type: String,
+    pub reduce_only: bool,
+    pub is_trigger: bool,
+    pub trigger_condition: String,
+    pub trigger_px: String,
+    pub is_position_tpsl: bool,
+    pub tif: Option<String>,
+    pub cloid: Option<String>,
+    /// Take profit and stop loss orders placed together with this one.
+    #[serde(default)]
+    pub children: Vec<FrontendOpenOrdersResponse>,
+}
+
+/// An order and its latest status, e.g. `filled` or `canceled`.
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoricalOrdersResponse {
+    pub order: FrontendOpenOrdersResponse,
+    pub status: String,
+    pub status_timestamp: u64,
+}
+
+/// Fee rates of a user, after referral and staking discounts.
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserFeesResponse {
+    pub daily_user_vlm: Vec<DailyUserVlm>,
+    /// Taker fee rate.
+    pub user_cross_rate: String,
+    /// Maker fee rate.
+    pub user_add_rate: String,
+    pub active_referral_discount: String,
+}
+
+/// Requests a user has used of the budget earned by its traded volume.
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserRateLimitResponse {
+    pub cum_vlm: String,
+    pub n_requests_used: u64,
+    pub n_requests_cap: u64,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct UserFundingResponse {
+    pub delta: FundingDelta,
+    pub hash: String,
+    pub time: u64,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct UserNonFundingLedgerUpdatesResponse {
+    pub delta: LedgerUpdate,
+    pub hash: String,
+    pub time: u64,
+}
+
 #[derive(serde::Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct UserFillsResponse {
@@ -35,6 +111,9 @@ pub struct UserFillsResponse {
     pub start_position: String,
     pub sz: String,
     pub time: u64,
+    /// Trade id, shared by the fills of both sides of a trade.
+    #[serde(default)]
+    pub tid: u64,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -88,3 +167,58 @@ pub struct CandlesSnapshotResponse {
     #[serde(rename = "n")]
     pub num_trades: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_responses() {
+        let orders: Vec<HistoricalOrdersResponse> = serde_json::from_str(
+            r#"[{"order":{"coin":"ETH","side":"A","limitPx":"2412.7","sz":"0.0","oid":1,
+            "timestamp":1724361546645,"triggerCondition":"N/A","isTrigger":false,"triggerPx":"0.0",
+            "children":[{"coin":"ETH","side":"B","limitPx":"2300.0","sz":"0.0076","oid":2,
+            "timestamp":1724361546645,"triggerCondition":"Price below 2300","isTrigger":true,
+            "triggerPx":"2300.0","children":[],"isPositionTpsl":false,"reduceOnly":true,
+            "orderType":"Stop Market","origSz":"0.0076","tif":null,"cloid":null}],
+            "isPositionTpsl":false,"reduceOnly":false,"orderType":"Limit","origSz":"0.0076",
+            "tif":"Alo","cloid":null},"status":"filled","statusTimestamp":1724361546645}]"#,
+        )
+        .unwrap();
+        assert_eq!(orders[0].status, "filled");
+        assert!(orders[0].order.children[0].is_trigger);
+
+        let fees: UserFeesResponse = serde_json::from_str(
+            r#"{"dailyUserVlm":[{"date":"2025-05-23","userCross":"0.0","userAdd":"12.5",
+            "exchange":"2852367.07"}],"feeSchedule":{"cross":"0.00045","add":"0.00015"},
+            "userCrossRate":"0.000315","userAddRate":"0.000105","activeReferralDiscount":"0.0"}"#,
+        )
+        .unwrap();
+        assert_eq!(fees.user_add_rate, "0.000105");
+
+        let rate_limit: UserRateLimitResponse = serde_json::from_str(
+            r#"{"cumVlm":"2854574.59","nRequestsUsed":2890,"nRequestsCap":2864574}"#,
+        )
+        .unwrap();
+        assert_eq!(rate_limit.n_requests_used, 2890);
+
+        let funding: Vec<UserFundingResponse> = serde_json::from_str(
+            r#"[{"delta":{"coin":"ETH","fundingRate":"0.0000417","szi":"49.1477",
+            "type":"funding","usdc":"-3.625312"},"hash":"0x00","time":1681222254710}]"#,
+        )
+        .unwrap();
+        assert_eq!(funding[0].delta.usdc, "-3.625312");
+
+        let ledger: Vec<UserNonFundingLedgerUpdatesResponse> = serde_json::from_str(
+            r#"[{"delta":{"type":"accountClassTransfer","usdc":"100.0","toPerp":true},
+            "hash":"0x01","time":1681222254710},
+            {"delta":{"type":"rewardsClaim","amount":"1.0"},"hash":"0x02","time":1681222254711}]"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            ledger[0].delta,
+            LedgerUpdate::AccountClassTransfer { to_perp: true, .. }
+        ));
+        assert!(matches!(ledger[1].delta, LedgerUpdate::Other));
+    }
+}
@@ -1,6 +1,7 @@
-use serde::Deserialize;
+use ethers::types::H160;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Leverage {
     #[serde(rename = "type")]
@@ -39,6 +40,17 @@ pub struct MarginSummary {
     pub total_raw_usd: String,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserTokenBalance {
+    pub coin: String,
+    pub token: usize,
+    /// Part of the total held by open orders.
+    pub hold: String,
+    pub total: String,
+    pub entry_ntl: String,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Level {
@@ -46,3 +58,86 @@ pub struct Level {
     pub px: String,
     pub sz: String,
 }
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyUserVlm {
+    pub date: String,
+    /// Taker volume of the user.
+    pub user_cross: String,
+    /// Maker volume of the user.
+    pub user_add: String,
+    /// Volume of the whole exchange.
+    pub exchange: String,
+}
+
+/// A funding payment of a position. `usdc` is negative when it was paid.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingDelta {
+    pub coin: String,
+    pub funding_rate: String,
+    pub szi: String,
+    pub usdc: String,
+}
+
+/// A change of the balances of an account other than a fill or a funding
+/// payment. Kinds of updates that aren't known yet are read as `Other`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LedgerUpdate {
+    Deposit {
+        usdc: String,
+    },
+    Withdraw {
+        usdc: String,
+        nonce: u64,
+        fee: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    AccountClassTransfer {
+        usdc: String,
+        to_perp: bool,
+    },
+    InternalTransfer {
+        usdc: String,
+        user: H160,
+        destination: H160,
+        fee: String,
+    },
+    SubAccountTransfer {
+        usdc: String,
+        user: H160,
+        destination: H160,
+    },
+    #[serde(rename_all = "camelCase")]
+    SpotTransfer {
+        token: String,
+        amount: String,
+        usdc_value: String,
+        user: H160,
+        destination: H160,
+        fee: String,
+    },
+    VaultDeposit {
+        vault: H160,
+        usdc: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    VaultWithdraw {
+        vault: H160,
+        user: H160,
+        requested_usd: String,
+        commission: String,
+        closing_cost: String,
+        basis: String,
+        net_withdrawn_usd: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Liquidation {
+        account_value: String,
+        leverage_type: String,
+    },
+    #[serde(other)]
+    Other,
+}
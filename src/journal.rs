@@ -0,0 +1,314 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{helpers::now_timestamp_ms, prelude::*, Error, EPSILON};
+
+/// Order activity of a market maker, as recorded in its journal.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JournalEvent {
+    /// An order is about to be sent.
+    OrderSent { is_buy: bool, px: f64, sz: f64 },
+    /// The exchange accepted an order and it's resting on the book.
+    OrderAck {
+        oid: u64,
+        is_buy: bool,
+        px: f64,
+        sz: f64,
+    },
+    /// The exchange rejected an order.
+    OrderRejected { is_buy: bool, error: String },
+    /// An order was filled, in part or in full.
+    Fill {
+        oid: u64,
+        is_buy: bool,
+        px: f64,
+        sz: f64,
+        fee: f64,
+        closed_pnl: f64,
+    },
+    /// An order is no longer open, because it was canceled or found to be
+    /// gone.
+    Cancel { oid: u64 },
+    /// The tracked state was reconciled with the exchange, which reported
+    /// this position.
+    Reconciled { position: f64 },
+}
+
+/// A journal event with the local time in milliseconds at which it was
+/// recorded.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JournalEntry {
+    pub time: u64,
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+/// An order the journal considers open.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JournalOrder {
+    pub is_buy: bool,
+    pub px: f64,
+    /// Size that hasn't been filled yet.
+    pub sz: f64,
+}
+
+/// Orders, position and PnL as of the last journal entry.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JournalState {
+    pub open_orders: BTreeMap<u64, JournalOrder>,
+    pub position: f64,
+    /// Sum of the closed PnL of all fills, before fees.
+    pub realized_pnl: f64,
+    pub fees: f64,
+    pub fill_count: u64,
+}
+
+impl JournalState {
+    pub fn apply(&mut self, event: &JournalEvent) {
+        match *event {
+            JournalEvent::OrderSent { .. } | JournalEvent::OrderRejected { .. } => {}
+            JournalEvent::OrderAck {
+                oid,
+                is_buy,
+                px,
+                sz,
+            } => {
+                self.open_orders
+                    .insert(oid, JournalOrder { is_buy, px, sz });
+            }
+            JournalEvent::Fill {
+                oid,
+                is_buy,
+                sz,
+                fee,
+                closed_pnl,
+                ..
+            } => {
+                if let Some(order) = self.open_orders.get_mut(&oid) {
+                    order.sz -= sz;
+                    if order.sz < EPSILON {
+                        self.open_orders.remove(&oid);
+                    }
+                }
+                self.position += if is_buy { sz } else { -sz };
+                self.realized_pnl += closed_pnl;
+                self.fees += fee;
+                self.fill_count += 1;
+            }
+            JournalEvent::Cancel { oid } => {
+                self.open_orders.remove(&oid);
+            }
+            JournalEvent::Reconciled { position } => self.position = position,
+        }
+    }
+}
+
+/// Append-only journal of the order activity of a market maker, stored as
+/// one JSON encoded `JournalEntry` per line.
+///
+/// Every entry is written to the file as soon as it's appended, so that
+/// replaying the journal after a crash restores the state up to the last
+/// event.
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+impl Journal {
+    /// Opens the journal at `path` for appending, creating it and its
+    /// directory if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        let io_error = |e: std::io::Error| Error::Io(format!("{}: {e}", path.display()));
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(io_error)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(io_error)?;
+
+        // Terminate a line left incomplete by a crash, so that it doesn't
+        // corrupt the next entry
+        let mut last = [0; 1];
+        if file.seek(SeekFrom::End(0)).map_err(io_error)? > 0 {
+            file.seek(SeekFrom::End(-1)).map_err(io_error)?;
+            file.read_exact(&mut last).map_err(io_error)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n").map_err(io_error)?;
+            }
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `event` to the end of the journal.
+    pub fn append(&mut self, event: JournalEvent) -> Result<()> {
+        let entry = JournalEntry {
+            time: now_timestamp_ms(),
+            event,
+        };
+        let mut line =
+            serde_json::to_string(&entry).map_err(|e| Error::JsonParse(e.to_string()))?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(|e| Error::Io(format!("{}: {e}", self.path.display())))
+    }
+
+    /// Reads all entries of the journal at `path`. A missing journal has no
+    /// entries, and lines that can't be parsed, e.g. the last line written
+    /// before a crash, are skipped.
+    pub fn read(path: &Path) -> Result<Vec<JournalEntry>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::Io(format!("{}: {e}", path.display()))),
+        };
+
+        let mut entries = Vec::new();
+        for (line_number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| Error::Io(format!("{}: {e}", path.display())))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!(
+                    "Skipping invalid journal entry at {}:{}: {e}",
+                    path.display(),
+                    line_number + 1
+                ),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Replays the journal at `path` into the state it describes.
+    pub fn replay(path: &Path) -> Result<JournalState> {
+        let mut state = JournalState::default();
+        for entry in Self::read(path)? {
+            state.apply(&entry.event);
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("journal-{}", uuid::Uuid::new_v4()))
+            .join("ETH.jsonl")
+    }
+
+    #[test]
+    fn test_replay_restores_orders_position_and_pnl() -> Result<()> {
+        let path = journal_path();
+        assert_eq!(Journal::replay(&path)?, JournalState::default());
+
+        let mut journal = Journal::open(&path)?;
+        for event in [
+            JournalEvent::OrderSent {
+                is_buy: true,
+                px: 99.0,
+                sz: 2.0,
+            },
+            JournalEvent::OrderAck {
+                oid: 1,
+                is_buy: true,
+                px: 99.0,
+                sz: 2.0,
+            },
+            JournalEvent::OrderAck {
+                oid: 2,
+                is_buy: false,
+                px: 101.0,
+                sz: 2.0,
+            },
+            JournalEvent::Fill {
+                oid: 1,
+                is_buy: true,
+                px: 99.0,
+                sz: 0.5,
+                fee: 0.01,
+                closed_pnl: 0.0,
+            },
+            JournalEvent::Fill {
+                oid: 2,
+                is_buy: false,
+                px: 101.0,
+                sz: 2.0,
+                fee: 0.03,
+                closed_pnl: 1.0,
+            },
+            JournalEvent::Cancel { oid: 3 },
+        ] {
+            journal.append(event)?;
+        }
+
+        let state = Journal::replay(&path)?;
+        assert_eq!(
+            state.open_orders.into_iter().collect::<Vec<_>>(),
+            vec![(
+                1,
+                JournalOrder {
+                    is_buy: true,
+                    px: 99.0,
+                    sz: 1.5
+                }
+            )]
+        );
+        assert!((state.position + 1.5).abs() < EPSILON);
+        assert!((state.realized_pnl - 1.0).abs() < EPSILON);
+        assert!((state.fees - 0.04).abs() < EPSILON);
+        assert_eq!(state.fill_count, 2);
+
+        fs::remove_dir_all(path.parent().unwrap()).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_incomplete_line_is_skipped() -> Result<()> {
+        let path = journal_path();
+        let mut journal = Journal::open(&path)?;
+        journal.append(JournalEvent::Reconciled { position: 1.0 })?;
+        drop(journal);
+
+        // A crash in the middle of writing an entry
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .map_err(|e| Error::Io(e.to_string()))?;
+        file.write_all(br#"{"time":1,"event":"can"#)
+            .map_err(|e| Error::Io(e.to_string()))?;
+
+        let mut journal = Journal::open(&path)?;
+        journal.append(JournalEvent::Reconciled { position: 2.0 })?;
+
+        let entries = Journal::read(&path)?;
+        assert_eq!(entries.len(), 2);
+        assert!((Journal::replay(&path)?.position - 2.0).abs() < EPSILON);
+
+        fs::remove_dir_all(path.parent().unwrap()).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(())
+    }
+}
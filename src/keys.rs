@@ -0,0 +1,137 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ethers::signers::LocalWallet;
+use zeroize::Zeroizing;
+
+use crate::{prelude::*, Error};
+
+/// Environment variable holding a hex encoded private key.
+pub const PRIVATE_KEY_ENV: &str = "HL_PRIVATE_KEY";
+/// Environment variable holding the path of an encrypted JSON keystore.
+pub const KEYSTORE_ENV: &str = "HL_KEYSTORE";
+/// Environment variable holding the passphrase of the keystore. Without it
+/// the passphrase is prompted for.
+pub const KEYSTORE_PASSWORD_ENV: &str = "HL_KEYSTORE_PASSWORD";
+
+/// Where the passphrase of an encrypted keystore comes from.
+#[derive(Debug, Clone)]
+pub enum KeystorePassword {
+    Env(String),
+    File(PathBuf),
+    /// Asks for the passphrase on the terminal without echoing it.
+    Prompt,
+}
+
+impl KeystorePassword {
+    pub(crate) fn read(&self, keystore: &Path) -> Result<Zeroizing<String>> {
+        let password = match self {
+            Self::Env(variable) => read_env(variable)?,
+            Self::File(path) => read_file(path)?,
+            Self::Prompt => Zeroizing::new(
+                rpassword::prompt_password(format!("Passphrase for {}: ", keystore.display()))
+                    .map_err(|e| Error::Wallet(format!("passphrase prompt: {e}")))?,
+            ),
+        };
+        Ok(Zeroizing::new(
+            password.trim_end_matches(['\r', '\n']).to_string(),
+        ))
+    }
+}
+
+fn read_env(variable: &str) -> Result<Zeroizing<String>> {
+    std::env::var(variable)
+        .map(Zeroizing::new)
+        .map_err(|e| Error::Wallet(format!("environment variable {variable}: {e}")))
+}
+
+fn read_file(path: &Path) -> Result<Zeroizing<String>> {
+    fs::read_to_string(path)
+        .map(Zeroizing::new)
+        .map_err(|e| Error::Wallet(format!("{}: {e}", path.display())))
+}
+
+fn parse_key(key: &str) -> Result<LocalWallet> {
+    key.trim()
+        .parse()
+        .map_err(|e| Error::PrivateKeyParse(format!("{e}")))
+}
+
+/// Reads a hex encoded private key from an environment variable.
+pub fn wallet_from_env(variable: &str) -> Result<LocalWallet> {
+    parse_key(&read_env(variable)?)
+}
+
+/// Reads a hex encoded private key from a file.
+pub fn wallet_from_file(path: &Path) -> Result<LocalWallet> {
+    parse_key(&read_file(path)?)
+}
+
+/// Decrypts an encrypted JSON keystore as written by `geth` or `cast wallet`.
+pub fn wallet_from_keystore(path: &Path, password: &KeystorePassword) -> Result<LocalWallet> {
+    let password = password.read(path)?;
+    LocalWallet::decrypt_keystore(path, password.as_str())
+        .map_err(|e| Error::Wallet(format!("{}: {e}", path.display())))
+}
+
+/// Loads the wallet from the keystore at `HL_KEYSTORE` if it is set, and
+/// from the private key in `HL_PRIVATE_KEY` otherwise. The keystore
+/// passphrase is taken from `HL_KEYSTORE_PASSWORD` or prompted for.
+pub fn load_wallet() -> Result<LocalWallet> {
+    match std::env::var_os(KEYSTORE_ENV) {
+        Some(path) => {
+            let password = if std::env::var_os(KEYSTORE_PASSWORD_ENV).is_some() {
+                KeystorePassword::Env(KEYSTORE_PASSWORD_ENV.to_string())
+            } else {
+                KeystorePassword::Prompt
+            };
+            wallet_from_keystore(Path::new(&path), &password)
+        }
+        None => wallet_from_env(PRIVATE_KEY_ENV),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::Signer;
+
+    #[test]
+    fn test_keystore_round_trip() -> Result<()> {
+        let directory = std::env::temp_dir().join(format!("keystore-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&directory).map_err(|e| Error::Io(e.to_string()))?;
+        let (wallet, name) =
+            LocalWallet::new_keystore(&directory, &mut rand::thread_rng(), "passphrase", None)
+                .map_err(|e| Error::Wallet(e.to_string()))?;
+        let password_file = directory.join("password");
+        fs::write(&password_file, "passphrase\n").map_err(|e| Error::Io(e.to_string()))?;
+
+        let keystore = directory.join(name);
+        let loaded =
+            wallet_from_keystore(&keystore, &KeystorePassword::File(password_file.clone()))?;
+        assert_eq!(loaded.address(), wallet.address());
+
+        fs::write(&password_file, "wrong").map_err(|e| Error::Io(e.to_string()))?;
+        let loaded = wallet_from_keystore(&keystore, &KeystorePassword::File(password_file));
+        assert!(matches!(loaded, Err(Error::Wallet(_))));
+
+        fs::remove_dir_all(directory).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_key_is_not_echoed() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("key-{}", uuid::Uuid::new_v4()));
+        fs::write(&path, "not a key").map_err(|e| Error::Io(e.to_string()))?;
+        let result = wallet_from_file(&path);
+        fs::remove_file(&path).map_err(|e| Error::Io(e.to_string()))?;
+
+        let Err(e) = result else {
+            panic!("key should be invalid");
+        };
+        assert!(!e.to_string().contains("not a key"));
+        Ok(())
+    }
+}
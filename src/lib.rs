@@ -1,21 +1,44 @@
 #![deny(unreachable_pub)]
+mod agents;
+mod config;
 mod consts;
 mod errors;
+mod events;
 mod exchange;
 mod helpers;
 mod info;
+mod journal;
+mod keys;
 mod market_maker;
 mod meta;
+mod metrics;
 mod prelude;
 mod proxy_digest;
 mod req;
 mod signature;
+mod simulation;
 mod ws;
+pub use agents::{Agent, AgentManager, AgentSigner, AgentStore};
+pub use config::{
+    AgentConfig, ConfigWatcher, LogFormat, LoggingConfig, MarketConfig, MarketMakerConfig,
+    WalletConfig, WebsocketOrdersConfig,
+};
 pub use consts::{EPSILON, LOCAL_API_URL, MAINNET_API_URL, TESTNET_API_URL};
 pub use errors::Error;
+pub use events::{next_correlation_id, write_event_log, EventRecord, TradingEvent};
 pub use exchange::*;
-pub use helpers::{bps_diff, truncate_float, BaseUrl};
+pub use helpers::{bps_diff, now_timestamp_ms, truncate_float, BaseUrl};
 pub use info::{info_client::*, *};
-pub use market_maker::{Input, MarketMaker, RestingOrder};
-pub use meta::{AssetMeta, Meta};
+pub use journal::{Journal, JournalEntry, JournalEvent, JournalOrder, JournalState};
+pub use keys::{
+    load_wallet, wallet_from_env, wallet_from_file, wallet_from_keystore, KeystorePassword,
+    KEYSTORE_ENV, KEYSTORE_PASSWORD_ENV, PRIVATE_KEY_ENV,
+};
+pub use market_maker::{Input, MarginState, MarketMaker, RestingOrder, SpotBalances};
+pub use meta::{
+    AssetCtx, AssetMeta, Meta, SpotAssetCtx, SpotAssetMeta, SpotMeta, TokenInfo, SPOT_ASSET_OFFSET,
+};
+pub use metrics::{gather_metrics, serve_metrics, AssetMetrics};
+pub use signature::ActionSigner;
+pub use simulation::*;
 pub use ws::*;
@@ -1,19 +1,43 @@
 #![warn(clippy::all, clippy::nursery, clippy::pedantic)]
 
+use chrono::Utc;
 use ethers::{
     signers::{LocalWallet, Signer},
     types::H160,
 };
 use gxhash::{HashMap, HashMapExt};
-use log::{error, info};
-use tokio::sync::mpsc::unbounded_channel;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedSender},
+    watch,
+};
+use tracing::{error, info, warn};
 
 use crate::{
-    bps_diff, truncate_float, BaseUrl, ClientCancelRequest, ClientLimit, ClientOrder,
-    ClientOrderRequest, ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus, InfoClient,
-    Message, Subscription, EPSILON,
+    bounded_channel, bps_diff,
+    metrics::{last_ws_dispatch, observe_stage, stage, stage_timer},
+    next_correlation_id, truncate_float, ActiveAssetCtxData, AssetMetrics, BaseUrl,
+    ClientCancelRequest, ClientLimit, ClientOrder, ClientOrderRequest, Error, EventRecord,
+    ExchangeApi, ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus, InfoClient, Journal,
+    JournalEvent, MarketConfig, MarketDataApi, Message, OverflowPolicy, Subscription, TradingEvent,
+    UserStateResponse, EPSILON,
 };
 
+/// How often margin and liquidation data is refreshed while quoting, in
+/// addition to the refresh after every fill.
+const ACCOUNT_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How far back the funding history is searched for the latest rate.
+const FUNDING_LOOKBACK_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Market data queued while quotes are being updated. Mids and asset
+/// contexts are conflated, so only the latest is processed after a slow
+/// update.
+const MARKET_DATA_BUFFER: usize = 16;
+
 #[derive(Debug)]
 pub struct RestingOrder {
     pub oid: u64,
@@ -28,37 +52,183 @@ pub struct Input {
     pub max_bps_diff: u16,
     pub max_absolute_position_size: f64,
     pub decimals: u32,
+    pub leverage: u32,
+    pub is_cross: bool,
+    pub max_margin_utilization: f64,
+    pub min_liquidation_distance_bps: u16,
+    /// Hours of funding at the predicted rate by which quotes are shifted,
+    /// to build up inventory on the side that receives funding. Quotes
+    /// aren't skewed if it's zero.
+    pub funding_skew_hours: f64,
     pub wallet: LocalWallet,
+    /// Vault or subaccount to trade for, with `wallet` as its leader or
+    /// master. The wallet's own account is traded if it's `None`.
+    pub vault_address: Option<H160>,
+}
+
+/// Margin and liquidation data of the account, as of the last user state
+/// refresh.
+#[derive(Debug, Default)]
+pub struct MarginState {
+    pub account_value: f64,
+    pub margin_used: f64,
+    pub withdrawable: f64,
+    pub liquidation_price: Option<f64>,
+    /// Unrealized profit and loss of the position.
+    pub unrealized_pnl: f64,
+}
+
+/// Token balances of the traded spot pair, as of the last refresh.
+#[derive(Debug, Default)]
+pub struct SpotBalances {
+    pub base_token: String,
+    pub quote_token: String,
+    /// Balance of the base token, including the part held by open orders.
+    pub base_total: f64,
+    /// Balances that aren't held by open orders.
+    pub base_available: f64,
+    pub quote_available: f64,
+}
+
+impl MarginState {
+    /// Share of the account value that is currently used as margin.
+    #[must_use]
+    pub fn utilization(&self) -> f64 {
+        if self.account_value > EPSILON {
+            self.margin_used / self.account_value
+        } else {
+            1.0
+        }
+    }
 }
 
-pub struct MarketMaker {
+pub struct MarketMaker<E = ExchangeClient, M = InfoClient> {
     pub asset: String,
     pub target_liquidity: f64,
     pub half_spread: u16,
     pub max_bps_diff: u16,
     pub max_absolute_position_size: f64,
     pub decimals: u32,
+    pub sz_decimals: u32,
+    pub leverage: u32,
+    pub is_cross: bool,
+    pub max_margin_utilization: f64,
+    pub min_liquidation_distance_bps: u16,
+    pub funding_skew_hours: f64,
+    pub margin: MarginState,
+    /// Balances of the base and quote tokens if the asset is a spot pair,
+    /// whose base token balance is the position.
+    pub spot: Option<SpotBalances>,
+    pub last_account_refresh: Instant,
     pub lower_resting: RestingOrder,
     pub upper_resting: RestingOrder,
     pub cur_position: f64,
     pub latest_mid_price: f64,
-    pub info_client: InfoClient,
-    pub exchange_client: ExchangeClient,
+    /// Predicted hourly funding rate of the perp, positive when longs pay
+    /// shorts.
+    pub funding_rate: f64,
+    /// Funding the position is expected to have earned, accrued at the
+    /// predicted rates. It's tracked separately from the trading profit and
+    /// loss.
+    pub expected_funding_pnl: f64,
+    pub info_client: M,
+    pub exchange_client: E,
     pub user_address: H160,
     pub active_orders: HashMap<u64, bool>, // Track active order IDs and their buy/sell status
+    /// Sum of the closed profit and loss of all fills, before fees.
+    pub realized_pnl: f64,
+    pub fees: f64,
+    config_updates: Option<watch::Receiver<MarketConfig>>,
+    journal: Option<Journal>,
+    metrics: AssetMetrics,
+    events: Option<UnboundedSender<EventRecord>>,
+    /// Id of the latest quote decision.
+    correlation_id: u64,
+    /// Ids of the quote decisions that placed the tracked orders.
+    order_correlation: HashMap<u64, u64>,
+    /// When the mid price the current quotes are based on was received.
+    tick_received: Option<Instant>,
+    /// When funding was last accrued to `expected_funding_pnl`.
+    last_funding_accrual: Option<Instant>,
 }
 
 impl MarketMaker {
+    /// Creates a market maker that trades on the exchange at `base_url`.
+    ///
     /// # Errors
     ///
-    /// Returns `Err` if the exchange or info clients can't be created.
-    pub async fn new(input: Input) -> Result<Self, Box<dyn std::error::Error>> {
-        let user_address = input.wallet.address();
+    /// Returns `Err` if the exchange or info clients can't be created, the
+    /// asset is unknown or the exchange rejects the leverage update.
+    pub async fn new(input: Input, base_url: BaseUrl) -> Result<Self, Box<dyn std::error::Error>> {
+        let info_client = InfoClient::new(None, Some(base_url)).await?;
+        let exchange_client = ExchangeClient::new(
+            None,
+            input.wallet.clone(),
+            Some(base_url),
+            None,
+            input.vault_address,
+        )
+        .await?;
+        Self::with_clients(input, info_client, exchange_client).await
+    }
 
-        let info_client = InfoClient::new(None, Some(BaseUrl::Mainnet)).await?;
+    /// Creates a market maker for the account of `input.wallet` that signs
+    /// its orders with `signer`, e.g. an approved agent of the account.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the exchange or info clients can't be created, the
+    /// asset is unknown or the exchange rejects the leverage update.
+    pub async fn with_signer(
+        input: Input,
+        base_url: BaseUrl,
+        signer: Arc<dyn crate::ActionSigner>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let info_client = InfoClient::new(None, Some(base_url)).await?;
         let exchange_client =
-            ExchangeClient::new(None, input.wallet, Some(BaseUrl::Mainnet), None, None).await?;
+            ExchangeClient::with_signer(None, signer, Some(base_url), None, input.vault_address)
+                .await?;
+        Self::with_clients(input, info_client, exchange_client).await
+    }
+}
+
+/// Sets a strategy parameter, logging the change.
+fn update_param<T: PartialEq + Display>(asset: &str, name: &str, param: &mut T, value: T) {
+    if *param != value {
+        info!("Updated {asset} {name}: {param} -> {value}");
+        *param = value;
+    }
+}
+
+impl<E: ExchangeApi, M: MarketDataApi> MarketMaker<E, M> {
+    /// Creates a market maker that trades through the given clients, e.g.
+    /// a simulated exchange or mocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the asset is unknown, the exchange rejects the
+    /// leverage update of a perp or the initial state can't be fetched.
+    pub async fn with_clients(
+        input: Input,
+        info_client: M,
+        exchange_client: E,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let user_address = input
+            .vault_address
+            .unwrap_or_else(|| input.wallet.address());
+        let sz_decimals = exchange_client
+            .sz_decimals(&input.asset)
+            .ok_or(crate::Error::AssetNotFound)?;
+
+        let spot = exchange_client
+            .spot_tokens(&input.asset)
+            .map(|(base_token, quote_token)| SpotBalances {
+                base_token,
+                quote_token,
+                ..SpotBalances::default()
+            });
 
+        let metrics = AssetMetrics::new(&input.asset);
         let mut market_maker = Self {
             asset: input.asset,
             target_liquidity: input.target_liquidity,
@@ -66,6 +236,15 @@ impl MarketMaker {
             max_bps_diff: input.max_bps_diff,
             max_absolute_position_size: input.max_absolute_position_size,
             decimals: input.decimals,
+            sz_decimals,
+            leverage: input.leverage,
+            is_cross: input.is_cross,
+            max_margin_utilization: input.max_margin_utilization,
+            min_liquidation_distance_bps: input.min_liquidation_distance_bps,
+            funding_skew_hours: input.funding_skew_hours,
+            margin: MarginState::default(),
+            spot,
+            last_account_refresh: Instant::now(),
             lower_resting: RestingOrder {
                 oid: 0,
                 position: 0.0,
@@ -78,18 +257,60 @@ impl MarketMaker {
             },
             cur_position: 0.0,
             latest_mid_price: -1.0,
+            funding_rate: 0.0,
+            expected_funding_pnl: 0.0,
             info_client,
             exchange_client,
             user_address,
             active_orders: HashMap::new(),
+            realized_pnl: 0.0,
+            fees: 0.0,
+            config_updates: None,
+            journal: None,
+            metrics,
+            events: None,
+            correlation_id: next_correlation_id(),
+            order_correlation: HashMap::new(),
+            tick_received: None,
+            last_funding_accrual: None,
         };
 
+        // Spot pairs are traded without leverage
+        if market_maker.spot.is_none() {
+            market_maker.apply_leverage().await?;
+        }
+
         // Fetch and update the state with open orders and positions
         market_maker.update_state().await?;
+        if market_maker.spot.is_none() {
+            market_maker.fetch_funding_rate().await;
+        }
 
         Ok(market_maker)
     }
 
+    /// Sets the configured leverage and margin mode for the asset.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the request fails or the exchange rejects it.
+    async fn apply_leverage(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let response = self
+            .exchange_client
+            .update_leverage(self.leverage, &self.asset, self.is_cross)
+            .await?;
+        if let ExchangeResponseStatus::Err(e) = response {
+            return Err(format!("Error updating leverage for {}: {e}", self.asset).into());
+        }
+        info!(
+            "Set {} leverage to {}x ({})",
+            self.asset,
+            self.leverage,
+            if self.is_cross { "cross" } else { "isolated" }
+        );
+        Ok(())
+    }
+
     /// Updates state with open orders and positions.
     ///
     /// # Errors
@@ -134,60 +355,364 @@ impl MarketMaker {
     /// Returns `Err` if there's an error fetching the user state from the
     /// exchange or parsing the position value.
     async fn fetch_current_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.spot.is_some() {
+            return self.refresh_spot_balances().await;
+        }
         let user_state = self.info_client.user_state(self.user_address).await?;
         if let Some(position) = user_state
             .asset_positions
             .iter()
-            .find(|&pos| pos.type_string == self.asset)
+            .find(|&pos| pos.position.coin == self.asset)
         {
             self.cur_position = position.position.szi.parse()?;
         }
+        self.metrics.set_position(self.cur_position);
+        self.update_margin_state(&user_state)?;
         Ok(())
     }
 
-    pub async fn start(&mut self) {
-        let (sender, mut receiver) = unbounded_channel();
+    /// Fetches the user state and updates the margin data without touching
+    /// the tracked position. The token balances are refreshed instead for a
+    /// spot pair.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if there's an error fetching the user state from the
+    /// exchange or parsing the margin values.
+    async fn refresh_margin_state(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.spot.is_some() {
+            return self.refresh_spot_balances().await;
+        }
+        let user_state = self.info_client.user_state(self.user_address).await?;
+        self.update_margin_state(&user_state)
+    }
 
-        // Subscribe to UserEvents for fills
-        if let Err(e) = self
+    /// Updates margin usage, available margin and the liquidation price of
+    /// the position from a user state response.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if one of the margin values can't be parsed.
+    fn update_margin_state(
+        &mut self,
+        user_state: &UserStateResponse,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let summary = if self.is_cross {
+            &user_state.cross_margin_summary
+        } else {
+            &user_state.margin_summary
+        };
+        let position = user_state
+            .asset_positions
+            .iter()
+            .find(|&pos| pos.position.coin == self.asset);
+        let liquidation_price = position
+            .and_then(|pos| pos.position.liquidation_px.as_ref())
+            .map(|px| px.parse::<f64>())
+            .transpose()?;
+        let unrealized_pnl = position
+            .map(|pos| pos.position.unrealized_pnl.parse::<f64>())
+            .transpose()?
+            .unwrap_or_default();
+
+        self.margin = MarginState {
+            account_value: summary.account_value.parse()?,
+            margin_used: summary.total_margin_used.parse()?,
+            withdrawable: user_state.withdrawable.parse()?,
+            liquidation_price,
+            unrealized_pnl,
+        };
+        self.last_account_refresh = Instant::now();
+        self.update_pnl_metrics();
+        Ok(())
+    }
+
+    /// Fetches the token balances of the spot pair and takes the base token
+    /// balance as the position.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if there's an error fetching the balances from the
+    /// exchange or parsing them.
+    async fn refresh_spot_balances(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let spot_state = self.info_client.spot_user_state(self.user_address).await?;
+        let Some(spot) = &mut self.spot else {
+            return Ok(());
+        };
+        // Returns the total and the held balance of `token`
+        let balance = |token: &str| -> Result<(f64, f64), std::num::ParseFloatError> {
+            spot_state
+                .balances
+                .iter()
+                .find(|balance| balance.coin == token)
+                .map_or(Ok((0.0, 0.0)), |balance| {
+                    Ok((balance.total.parse()?, balance.hold.parse()?))
+                })
+        };
+        let (base_total, base_hold) = balance(&spot.base_token)?;
+        let (quote_total, quote_hold) = balance(&spot.quote_token)?;
+        spot.base_total = base_total;
+        spot.base_available = base_total - base_hold;
+        spot.quote_available = quote_total - quote_hold;
+
+        self.cur_position = base_total;
+        self.metrics.set_position(self.cur_position);
+        self.last_account_refresh = Instant::now();
+        Ok(())
+    }
+
+    /// Starts from the latest funding rate of the past day until live asset
+    /// context updates arrive. The rate stays at zero if it can't be fetched.
+    async fn fetch_funding_rate(&mut self) {
+        let start_time = u64::try_from(Utc::now().timestamp_millis())
+            .unwrap_or_default()
+            .saturating_sub(FUNDING_LOOKBACK_MS);
+        match self
             .info_client
-            .subscribe(
-                Subscription::UserEvents {
-                    user: self.user_address,
-                },
-                sender.clone(),
-            )
+            .funding_history(self.asset.clone(), start_time, None)
             .await
         {
-            error!("Error subscribing to UserEvents: {:?}", e);
-            return;
+            Ok(history) => {
+                if let Some(latest) = history.iter().max_by_key(|funding| funding.time) {
+                    match latest.funding_rate.parse() {
+                        Ok(funding_rate) => self.funding_rate = funding_rate,
+                        Err(e) => warn!("Invalid funding rate for {}: {e}", self.asset),
+                    }
+                }
+            }
+            Err(e) => warn!("Error fetching funding history of {}: {e}", self.asset),
         }
+        self.metrics
+            .set_funding(self.funding_rate, self.expected_funding_pnl);
+    }
 
-        // Subscribe to AllMids so we can market make around the mid price
-        if let Err(e) = self
-            .info_client
-            .subscribe(Subscription::AllMids, sender)
-            .await
-        {
-            error!("Error subscribing to AllMids: {:?}", e);
+    /// Accrues the funding the position is expected to have earned since the
+    /// last accrual, at the predicted rate and the latest mid price.
+    fn accrue_funding(&mut self) {
+        let now = Instant::now();
+        if let Some(last_funding_accrual) = self.last_funding_accrual {
+            if self.spot.is_none() && self.latest_mid_price > 0.0 {
+                let hours = now.duration_since(last_funding_accrual).as_secs_f64() / 3600.0;
+                // Longs pay shorts when the rate is positive
+                self.expected_funding_pnl -=
+                    self.cur_position * self.latest_mid_price * self.funding_rate * hours;
+            }
+        }
+        self.last_funding_accrual = Some(now);
+        self.metrics
+            .set_funding(self.funding_rate, self.expected_funding_pnl);
+    }
+
+    /// Takes the predicted funding rate from an asset context update.
+    fn update_funding_rate(&mut self, active_asset_ctx: &ActiveAssetCtxData) {
+        if active_asset_ctx.coin != self.asset {
             return;
         }
+        match active_asset_ctx.ctx.funding.parse() {
+            Ok(funding_rate) => {
+                // Funding until now accrued at the previous rate
+                self.accrue_funding();
+                self.funding_rate = funding_rate;
+                self.metrics
+                    .set_funding(self.funding_rate, self.expected_funding_pnl);
+            }
+            Err(e) => error!("Invalid funding rate for {}: {e}", self.asset),
+        }
+    }
 
-        while let Some(message) = receiver.recv().await {
-            self.process_message(message).await;
+    /// How far the quotes are shifted below the mid price, so that the side
+    /// that receives funding is more likely to fill: the ask if the rate is
+    /// positive and the bid if it's negative. It's limited to the half
+    /// spread so that both quotes stay on their side of the mid price.
+    fn funding_skew(&self, half_spread: f64) -> f64 {
+        if self.spot.is_some() {
+            return 0.0;
+        }
+        (self.latest_mid_price * self.funding_rate * self.funding_skew_hours)
+            .clamp(-half_spread, half_spread)
+    }
+
+    /// Restores the realized profit and loss from the journal at `path` and
+    /// reconciles the journaled orders and position with the state fetched
+    /// from the exchange, which takes precedence. All further order activity
+    /// is appended to the journal.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the journal can't be read or written.
+    pub fn restore_from_journal(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let state = Journal::replay(path)?;
+        let mut journal = Journal::open(path)?;
+        self.realized_pnl = state.realized_pnl;
+        self.fees = state.fees;
+        info!(
+            "Restored {} from {} fills in {}: realized PnL {}, fees {}",
+            self.asset,
+            state.fill_count,
+            path.display(),
+            self.realized_pnl,
+            self.fees
+        );
+
+        for &oid in state.open_orders.keys() {
+            if !self.active_orders.contains_key(&oid) {
+                warn!(
+                    "Journaled {} order {oid} is no longer open, it was filled or canceled while stopped",
+                    self.asset
+                );
+                journal.append(JournalEvent::Cancel { oid })?;
+            }
+        }
+        for (&oid, &is_buy) in &self.active_orders {
+            if state.open_orders.contains_key(&oid) {
+                continue;
+            }
+            warn!(
+                "Open {} order {oid} is missing from the journal",
+                self.asset
+            );
+            let resting = if is_buy {
+                &self.lower_resting
+            } else {
+                &self.upper_resting
+            };
+            let (px, sz) = if resting.oid == oid {
+                (resting.price, resting.position)
+            } else {
+                (0.0, 0.0)
+            };
+            journal.append(JournalEvent::OrderAck {
+                oid,
+                is_buy,
+                px,
+                sz,
+            })?;
+        }
+        if (state.position - self.cur_position).abs() > EPSILON {
+            warn!(
+                "Journaled {} position {} differs from the exchange position {}, fills were missed while stopped",
+                self.asset, state.position, self.cur_position
+            );
+        }
+        journal.append(JournalEvent::Reconciled {
+            position: self.cur_position,
+        })?;
+
+        self.journal = Some(journal);
+        Ok(())
+    }
+
+    /// Appends `event` to the journal, if there is one. Failing to journal
+    /// doesn't stop trading.
+    fn record(&mut self, event: JournalEvent) {
+        if let Some(journal) = &mut self.journal {
+            if let Err(e) = journal.append(event) {
+                error!("Error writing to journal {}: {e}", journal.path().display());
+            }
+        }
+    }
+
+    fn update_pnl_metrics(&self) {
+        self.metrics
+            .set_pnl(self.realized_pnl, self.fees, self.margin.unrealized_pnl);
+    }
+
+    /// Stops tracking an order that was canceled or is already gone.
+    fn remove_canceled(&mut self, oid: u64) {
+        self.active_orders.remove(&oid);
+        self.record(JournalEvent::Cancel { oid });
+        self.emit_for_order(oid, TradingEvent::Canceled { oid });
+        self.order_correlation.remove(&oid);
+    }
+
+    /// Starts tracking an order the exchange accepted.
+    fn order_acked(&mut self, oid: u64, is_buy: bool, px: f64, sz: f64) {
+        if let Some(tick_received) = self.tick_received {
+            observe_stage(stage::TICK_TO_TRADE, tick_received.elapsed());
+        }
+        self.active_orders.insert(oid, is_buy);
+        self.record(JournalEvent::OrderAck {
+            oid,
+            is_buy,
+            px,
+            sz,
+        });
+        self.emit(TradingEvent::OrderAck {
+            oid,
+            is_buy,
+            px,
+            sz,
+        });
+
+        // Forget orders that can't be filled anymore
+        let (lower_oid, upper_oid) = (self.lower_resting.oid, self.upper_resting.oid);
+        let active_orders = &self.active_orders;
+        self.order_correlation.retain(|oid, _| {
+            active_orders.contains_key(oid) || *oid == lower_oid || *oid == upper_oid
+        });
+        self.order_correlation.insert(oid, self.correlation_id);
+    }
+
+    /// Records an order that wasn't placed.
+    fn order_rejected(&mut self, is_buy: bool, error: String) {
+        self.record(JournalEvent::OrderRejected {
+            is_buy,
+            error: error.clone(),
+        });
+        self.emit(TradingEvent::OrderRejected { is_buy, error });
+    }
+
+    /// Sends events to `events`, e.g. to write them to a log with
+    /// `write_event_log`.
+    pub fn set_event_sink(&mut self, events: UnboundedSender<EventRecord>) {
+        self.events = Some(events);
+    }
+
+    /// Sends `event` as part of the current quote decision.
+    fn emit(&self, event: TradingEvent) {
+        self.emit_with(self.correlation_id, event);
+    }
+
+    /// Sends `event` as part of the quote decision that placed `oid`.
+    fn emit_for_order(&self, oid: u64, event: TradingEvent) {
+        let correlation_id = self
+            .order_correlation
+            .get(&oid)
+            .copied()
+            .unwrap_or(self.correlation_id);
+        self.emit_with(correlation_id, event);
+    }
+
+    fn emit_with(&self, correlation_id: u64, event: TradingEvent) {
+        if let Some(events) = &self.events {
+            // The receiver going away only ends the event log
+            let _ = events.send(EventRecord::new(&self.asset, correlation_id, event));
         }
-        error!("Receiver stream ended");
     }
 
-    async fn process_message(&mut self, message: Message) {
+    pub(crate) async fn process_message(&mut self, message: Message) {
         match message {
             Message::AllMids(all_mids) => {
+                let received = Instant::now();
+                let dispatch = last_ws_dispatch("allMids");
+                if let Some(dispatch) = dispatch {
+                    observe_stage(
+                        stage::CHANNEL_HANDOFF,
+                        received.saturating_duration_since(dispatch.dispatched),
+                    );
+                }
                 let all_mids = all_mids.data.mids;
                 if let Some(mid) = all_mids.get(&self.asset) {
                     if let Ok(mid) = mid.parse::<f64>() {
+                        self.accrue_funding();
                         self.latest_mid_price = mid;
+                        self.tick_received =
+                            Some(dispatch.map_or(received, |dispatch| dispatch.received));
                         // Check to see if we need to cancel or place any new orders
+                        let timer = stage_timer(stage::POTENTIALLY_UPDATE);
                         self.potentially_update().await;
+                        timer.observe_duration();
+                        self.tick_received = None;
                     } else {
                         error!(
                             "Invalid mid price format for asset {}: {:?}",
@@ -207,6 +732,20 @@ impl MarketMaker {
                 for fill in fills {
                     if fill.coin == self.asset {
                         let amount: f64 = fill.sz.parse().unwrap();
+                        let fee = fill.fee.parse().unwrap_or_default();
+                        let closed_pnl = fill.closed_pnl.parse().unwrap_or_default();
+                        let px = fill.px.parse().unwrap_or_default();
+                        self.realized_pnl += closed_pnl;
+                        self.fees += fee;
+                        self.metrics.record_fill(fill.side == "B", amount, px);
+                        self.record(JournalEvent::Fill {
+                            oid: fill.oid,
+                            is_buy: fill.side == "B",
+                            px,
+                            sz: amount,
+                            fee,
+                            closed_pnl,
+                        });
                         // Update our resting positions whenever we see a fill
                         if fill.side.eq("B") {
                             self.cur_position += amount;
@@ -225,11 +764,31 @@ impl MarketMaker {
                             }
                             info!("Fill: sold {amount} {}", self.asset);
                         }
+                        self.emit_for_order(
+                            fill.oid,
+                            TradingEvent::Fill {
+                                oid: fill.oid,
+                                is_buy: fill.side == "B",
+                                px,
+                                sz: amount,
+                                fee,
+                                closed_pnl,
+                                position: self.cur_position,
+                            },
+                        );
                     }
                 }
+                self.metrics.set_position(self.cur_position);
+                self.update_pnl_metrics();
+                if let Err(e) = self.refresh_margin_state().await {
+                    error!("Error refreshing margin state for {}: {e}", self.asset);
+                }
                 // Check to see if we need to cancel or place any new orders
                 self.potentially_update().await;
             }
+            Message::ActiveAssetCtx(active_asset_ctx) => {
+                self.update_funding_rate(&active_asset_ctx.data);
+            }
             _ => {
                 error!("Unsupported message type: {:?}", message);
             }
@@ -244,9 +803,11 @@ impl MarketMaker {
         }
 
         // Attempt to cancel the order
+        self.metrics.record_cancel();
+        self.emit_for_order(oid, TradingEvent::CancelSent { oid });
         let cancel = self
             .exchange_client
-            .cancel(ClientCancelRequest { asset, oid }, None)
+            .cancel(ClientCancelRequest { asset, oid })
             .await;
 
         match cancel {
@@ -261,7 +822,7 @@ impl MarketMaker {
                         } else {
                             match cancel.statuses[0].clone() {
                                 ExchangeDataStatus::Success => {
-                                    self.active_orders.remove(&oid); // Remove from active orders
+                                    self.remove_canceled(oid);
                                     return true;
                                 }
                                 ExchangeDataStatus::Error(e) => {
@@ -270,9 +831,13 @@ impl MarketMaker {
                                         || e.contains("already canceled")
                                         || e.contains("Order already filled")
                                     {
-                                        self.active_orders.remove(&oid); // Remove from active orders
+                                        self.remove_canceled(oid);
                                         return true;
                                     }
+                                    self.emit_for_order(
+                                        oid,
+                                        TradingEvent::CancelFailed { oid, error: e },
+                                    );
                                 }
                                 _ => unreachable!(),
                             }
@@ -290,12 +855,22 @@ impl MarketMaker {
                         || e.contains("already canceled")
                         || e.contains("Order already filled")
                     {
-                        self.active_orders.remove(&oid); // Remove from active orders
+                        self.remove_canceled(oid);
                         return true;
                     }
+                    self.emit_for_order(oid, TradingEvent::CancelFailed { oid, error: e });
                 }
             },
-            Err(e) => error!("Error with canceling: {e}"),
+            Err(e) => {
+                error!("Error with canceling: {e}");
+                self.emit_for_order(
+                    oid,
+                    TradingEvent::CancelFailed {
+                        oid,
+                        error: e.to_string(),
+                    },
+                );
+            }
         }
         false
     }
@@ -307,23 +882,31 @@ impl MarketMaker {
         price: f64,
         is_buy: bool,
     ) -> (f64, u64) {
+        self.record(JournalEvent::OrderSent {
+            is_buy,
+            px: price,
+            sz: amount,
+        });
+        self.emit(TradingEvent::OrderSent {
+            is_buy,
+            px: price,
+            sz: amount,
+        });
+        self.metrics.record_order();
         let order = self
             .exchange_client
-            .order(
-                ClientOrderRequest {
-                    asset,
-                    is_buy,
-                    reduce_only: false,
-                    limit_px: price,
-                    sz: amount,
-                    cloid: None,
-                    // Use ALO TIF for post-only
-                    order_type: ClientOrder::Limit(ClientLimit {
-                        tif: "Alo".to_string(),
-                    }),
-                },
-                None,
-            )
+            .order(ClientOrderRequest {
+                asset,
+                is_buy,
+                reduce_only: false,
+                limit_px: price,
+                sz: amount,
+                cloid: None,
+                // Use ALO TIF for post-only
+                order_type: ClientOrder::Limit(ClientLimit {
+                    tif: "Alo".to_string(),
+                }),
+            })
             .await;
 
         match order {
@@ -339,10 +922,12 @@ impl MarketMaker {
                         }
                         match order.statuses[0].clone() {
                             ExchangeDataStatus::Resting(order) => {
-                                self.active_orders.insert(order.oid, is_buy);
+                                self.order_acked(order.oid, is_buy, price, amount);
                                 return (amount, order.oid);
                             }
                             ExchangeDataStatus::Error(e) => {
+                                self.metrics.record_rejection(&e);
+                                self.order_rejected(is_buy, e.clone());
                                 if e.contains("Invalid Time in Force") {
                                     // Adjust to Hyperliquid's specific error message
                                     info!("Post-only order rejected. Will retry on next price update.");
@@ -362,20 +947,234 @@ impl MarketMaker {
                 }
                 ExchangeResponseStatus::Err(e) => {
                     error!("Error with placing order: {}", e);
+                    self.metrics.record_rejection(&e);
+                    self.order_rejected(is_buy, e);
                 }
             },
-            Err(e) => error!("Error with placing order: {}", e),
+            Err(Error::PostUnconfirmed(e)) => {
+                warn!("Order may have been placed without a response: {e}");
+                if let Some((amount_resting, oid)) =
+                    self.find_unconfirmed_order(is_buy, price).await
+                {
+                    self.order_acked(oid, is_buy, price, amount_resting);
+                    return (amount_resting, oid);
+                }
+                self.metrics.record_request_failure();
+                self.order_rejected(is_buy, e);
+            }
+            Err(e) => {
+                error!("Error with placing order: {}", e);
+                self.metrics.record_request_failure();
+                self.order_rejected(is_buy, e.to_string());
+            }
         }
 
         (0.0, 0) // Order placement failed
     }
 
+    /// Looks for an order that was sent without a response among the open
+    /// orders, returning its resting size and oid if it was placed.
+    async fn find_unconfirmed_order(&self, is_buy: bool, price: f64) -> Option<(f64, u64)> {
+        let open_orders = match self.info_client.open_orders(self.user_address).await {
+            Ok(open_orders) => open_orders,
+            Err(e) => {
+                error!("Error looking up unconfirmed order: {e}");
+                return None;
+            }
+        };
+        let order = open_orders.into_iter().find(|order| {
+            order.coin == self.asset
+                && (order.side == "B") == is_buy
+                && !self.active_orders.contains_key(&order.oid)
+                && order
+                    .limit_px
+                    .parse::<f64>()
+                    .is_ok_and(|px| (px - price).abs() < EPSILON)
+        })?;
+        info!("Unconfirmed order {} is resting", order.oid);
+        Some((order.sz.parse().unwrap_or_default(), order.oid))
+    }
+
+    /// Limits the amount of an order so that it only adds exposure as far as
+    /// the margin allows. The part of the order that reduces the current
+    /// position is never limited.
+    fn limit_by_margin(&self, amount: f64, price: f64, is_buy: bool) -> f64 {
+        if let Some(spot) = &self.spot {
+            return self.limit_by_balance(spot, amount, price, is_buy);
+        }
+        let reducing = if is_buy {
+            (-self.cur_position).max(0.0)
+        } else {
+            self.cur_position.max(0.0)
+        };
+        let increasing = (amount - reducing).max(0.0);
+        if increasing < EPSILON {
+            return amount;
+        }
+
+        let near_liquidation = self.margin.liquidation_price.is_some_and(|liquidation_px| {
+            bps_diff(self.latest_mid_price, liquidation_px) < self.min_liquidation_distance_bps
+        });
+        if near_liquidation {
+            warn!(
+                "{} mid {} is within {} bps of liquidation price {:?}, only reducing position",
+                self.asset,
+                self.latest_mid_price,
+                self.min_liquidation_distance_bps,
+                self.margin.liquidation_price
+            );
+            return amount.min(reducing);
+        }
+
+        // Scale linearly from full size at no margin usage down to nothing at
+        // the configured maximum utilization
+        let scale = if self.max_margin_utilization > EPSILON {
+            (1.0 - self.margin.utilization() / self.max_margin_utilization).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let affordable = if price > EPSILON {
+            (self.margin.withdrawable * f64::from(self.leverage) / price).max(0.0)
+        } else {
+            0.0
+        };
+        let limited = (increasing * scale).min(affordable);
+        if (limited - increasing).abs() < EPSILON {
+            return amount;
+        }
+        truncate_float(amount.min(reducing) + limited, self.sz_decimals, false)
+    }
+
+    /// Limits the amount of a spot order to what the token balances can pay
+    /// for. The balance held by the resting order on the same side counts as
+    /// available, as that order is replaced.
+    fn limit_by_balance(&self, spot: &SpotBalances, amount: f64, price: f64, is_buy: bool) -> f64 {
+        let affordable = if is_buy {
+            let available = self
+                .lower_resting
+                .position
+                .mul_add(self.lower_resting.price.max(0.0), spot.quote_available);
+            if price > EPSILON {
+                available / price
+            } else {
+                0.0
+            }
+        } else {
+            spot.base_available + self.upper_resting.position
+        };
+        if amount < affordable + EPSILON {
+            return amount;
+        }
+        truncate_float(affordable.max(0.0), self.sz_decimals, false)
+    }
+
+    /// Applies parameter updates sent through `updates` at the start of the
+    /// next quote update. Tracked orders and the position are kept.
+    pub fn set_config_updates(&mut self, updates: watch::Receiver<MarketConfig>) {
+        self.config_updates = Some(updates);
+    }
+
+    /// Applies the latest config update, if there is one. Invalid updates are
+    /// logged and ignored, and a leverage change the exchange rejects keeps
+    /// the previous leverage.
+    async fn apply_config_update(&mut self) {
+        let config = match &mut self.config_updates {
+            Some(updates) if updates.has_changed().unwrap_or(false) => {
+                updates.borrow_and_update().clone()
+            }
+            _ => return,
+        };
+        if config.asset != self.asset {
+            error!(
+                "Ignoring config update for {} sent to the {} market maker",
+                config.asset, self.asset
+            );
+            return;
+        }
+        let problems = config.problems();
+        if !problems.is_empty() {
+            error!(
+                "Ignoring invalid config update for {}: {}",
+                self.asset,
+                problems.join("; ")
+            );
+            return;
+        }
+
+        let asset = self.asset.clone();
+        update_param(
+            &asset,
+            "target_liquidity",
+            &mut self.target_liquidity,
+            config.target_liquidity,
+        );
+        update_param(
+            &asset,
+            "half_spread",
+            &mut self.half_spread,
+            config.half_spread,
+        );
+        update_param(
+            &asset,
+            "max_bps_diff",
+            &mut self.max_bps_diff,
+            config.max_bps_diff,
+        );
+        update_param(
+            &asset,
+            "max_absolute_position_size",
+            &mut self.max_absolute_position_size,
+            config.max_absolute_position_size,
+        );
+        update_param(&asset, "decimals", &mut self.decimals, config.decimals);
+        update_param(
+            &asset,
+            "max_margin_utilization",
+            &mut self.max_margin_utilization,
+            config.max_margin_utilization,
+        );
+        update_param(
+            &asset,
+            "min_liquidation_distance_bps",
+            &mut self.min_liquidation_distance_bps,
+            config.min_liquidation_distance_bps,
+        );
+        update_param(
+            &asset,
+            "funding_skew_hours",
+            &mut self.funding_skew_hours,
+            config.funding_skew_hours,
+        );
+
+        if self.spot.is_none()
+            && (config.leverage != self.leverage || config.is_cross != self.is_cross)
+        {
+            let previous = (self.leverage, self.is_cross);
+            (self.leverage, self.is_cross) = (config.leverage, config.is_cross);
+            if let Err(e) = self.apply_leverage().await {
+                error!("Keeping previous leverage for {asset}: {e}");
+                (self.leverage, self.is_cross) = previous;
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(asset = %self.asset))]
     async fn potentially_update(&mut self) {
+        self.apply_config_update().await;
+
+        if self.last_account_refresh.elapsed() > ACCOUNT_REFRESH_INTERVAL {
+            if let Err(e) = self.refresh_margin_state().await {
+                error!("Error refreshing margin state for {}: {e}", self.asset);
+            }
+        }
+
         let half_spread = (self.latest_mid_price * f64::from(self.half_spread)) / 10000.0;
-        // Determine prices to target from the half spread
+        // Determine prices to target from the half spread around the mid
+        // price, shifted towards accumulating the side that receives funding
+        let skew = self.funding_skew(half_spread);
         let (lower_price, upper_price) = (
-            self.latest_mid_price - half_spread,
-            self.latest_mid_price + half_spread,
+            self.latest_mid_price - skew - half_spread,
+            self.latest_mid_price - skew + half_spread,
         );
         let (mut lower_price, mut upper_price) = (
             truncate_float(lower_price, self.decimals, true),
@@ -395,12 +1194,37 @@ impl MarketMaker {
         let upper_order_amount =
             (self.max_absolute_position_size + self.cur_position).clamp(0.0, self.target_liquidity);
 
+        // Scale down the exposure we add as margin utilization rises
+        let lower_order_amount = self.limit_by_margin(lower_order_amount, lower_price, true);
+        let upper_order_amount = self.limit_by_margin(upper_order_amount, upper_price, false);
+
         // Determine if we need to cancel the resting order and put a new order up due to deviation
         let lower_change = (lower_order_amount - self.lower_resting.position).abs() > EPSILON
             || bps_diff(lower_price, self.lower_resting.price) > self.max_bps_diff;
         let upper_change = (upper_order_amount - self.upper_resting.position).abs() > EPSILON
             || bps_diff(upper_price, self.upper_resting.price) > self.max_bps_diff;
 
+        if lower_change || upper_change {
+            self.correlation_id = next_correlation_id();
+            self.emit(TradingEvent::QuoteDecision {
+                mid: self.latest_mid_price,
+                half_spread_bps: self.half_spread,
+                position: self.cur_position,
+                margin_utilization: if self.spot.is_some() {
+                    0.0
+                } else {
+                    self.margin.utilization()
+                },
+                bid_px: lower_price,
+                bid_sz: lower_order_amount,
+                ask_px: upper_price,
+                ask_sz: upper_order_amount,
+                funding_skew_bps: skew / self.latest_mid_price * 10_000.0,
+                replace_bid: lower_change,
+                replace_ask: upper_change,
+            });
+        }
+
         // Consider cancelling
         if self.lower_resting.oid != 0 && self.lower_resting.position > EPSILON && lower_change {
             let cancel = self
@@ -456,5 +1280,746 @@ impl MarketMaker {
                 );
             }
         }
+
+        self.update_quote_metrics();
+    }
+
+    /// Publishes the resting sizes and the spread between them.
+    fn update_quote_metrics(&self) {
+        self.metrics
+            .set_resting_size(true, self.lower_resting.position);
+        self.metrics
+            .set_resting_size(false, self.upper_resting.position);
+        let spread_bps = if self.lower_resting.position > EPSILON
+            && self.upper_resting.position > EPSILON
+            && self.latest_mid_price > EPSILON
+        {
+            (self.upper_resting.price - self.lower_resting.price) / self.latest_mid_price * 10_000.0
+        } else {
+            0.0
+        };
+        self.metrics.set_quoted_spread_bps(spread_bps);
+    }
+
+    #[tracing::instrument(
+        name = "market_maker",
+        skip_all,
+        fields(asset = %self.asset, wallet = ?self.user_address)
+    )]
+    pub async fn start(&mut self) {
+        // Fills are never dropped, while market data is conflated
+        let (user_events_sender, mut user_events) = unbounded_channel();
+        let (sender, mut market_data) =
+            bounded_channel(MARKET_DATA_BUFFER, OverflowPolicy::KeepLatest);
+
+        // Subscribe to UserEvents for fills
+        if let Err(e) = self
+            .info_client
+            .subscribe(
+                Subscription::UserEvents {
+                    user: self.user_address,
+                },
+                user_events_sender.into(),
+            )
+            .await
+        {
+            error!("Error subscribing to UserEvents: {:?}", e);
+            return;
+        }
+
+        // Subscribe to AllMids so we can market make around the mid price
+        if let Err(e) = self
+            .info_client
+            .subscribe(Subscription::AllMids, sender.clone().into())
+            .await
+        {
+            error!("Error subscribing to AllMids: {:?}", e);
+            return;
+        }
+
+        // Subscribe to the asset context of perps for the predicted funding
+        // rate, quoting with the last known rate if that fails
+        if self.spot.is_none() {
+            if let Err(e) = self
+                .info_client
+                .subscribe(
+                    Subscription::ActiveAssetCtx {
+                        coin: self.asset.clone(),
+                    },
+                    sender.into(),
+                )
+                .await
+            {
+                error!("Error subscribing to ActiveAssetCtx: {:?}", e);
+            }
+        }
+
+        loop {
+            let message = tokio::select! {
+                // Fills received by now are applied before newer market data
+                biased;
+                message = user_events.recv() => message,
+                message = market_data.recv() => message,
+            };
+            let Some(message) = message else {
+                break;
+            };
+            self.process_message(message).await;
+        }
+        error!("Receiver stream ended");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ExchangeDataStatuses, ExchangeResponse, FundingHistoryResponse, OpenOrdersResponse,
+        SpotUserStateResponse, SubscriptionSender, TradeInfo, User, UserData,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct PlacedOrder {
+        is_buy: bool,
+        limit_px: f64,
+        sz: f64,
+    }
+
+    #[derive(Default)]
+    struct MockCalls {
+        orders: Vec<PlacedOrder>,
+        cancels: Vec<u64>,
+        queried_addresses: Vec<H160>,
+        subscriptions: Vec<(Subscription, SubscriptionSender)>,
+    }
+
+    /// Exchange and info client that accepts every order and reports a flat
+    /// account with the given margin usage, and the given PURR and USDC
+    /// balances for the PURR/USDC spot pair.
+    #[derive(Clone, Default)]
+    struct MockClient {
+        calls: Arc<Mutex<MockCalls>>,
+        reject_leverage: bool,
+        margin_used: f64,
+        spot_balances: (f64, f64),
+        funding_rate: f64,
+        /// Orders rest without a response, as if the post timed out.
+        unconfirmed_orders: bool,
+    }
+
+    impl MockClient {
+        fn orders(&self) -> Vec<PlacedOrder> {
+            self.calls.lock().unwrap().orders.clone()
+        }
+    }
+
+    fn ok_response(statuses: Vec<ExchangeDataStatus>) -> ExchangeResponseStatus {
+        ExchangeResponseStatus::Ok(ExchangeResponse {
+            response_type: "default".to_string(),
+            data: Some(ExchangeDataStatuses { statuses }),
+        })
+    }
+
+    impl ExchangeApi for MockClient {
+        async fn bulk_order(
+            &self,
+            orders: Vec<ClientOrderRequest>,
+        ) -> crate::prelude::Result<ExchangeResponseStatus> {
+            let mut calls = self.calls.lock().unwrap();
+            let mut statuses = Vec::new();
+            for order in orders {
+                calls.orders.push(PlacedOrder {
+                    is_buy: order.is_buy,
+                    limit_px: order.limit_px,
+                    sz: order.sz,
+                });
+                statuses.push(ExchangeDataStatus::Resting(crate::exchange::RestingOrder {
+                    oid: calls.orders.len() as u64,
+                }));
+            }
+            drop(calls);
+            if self.unconfirmed_orders {
+                return Err(crate::Error::PostUnconfirmed("timed out".to_string()));
+            }
+            Ok(ok_response(statuses))
+        }
+
+        async fn bulk_cancel(
+            &self,
+            cancels: Vec<ClientCancelRequest>,
+        ) -> crate::prelude::Result<ExchangeResponseStatus> {
+            let statuses = cancels
+                .iter()
+                .map(|_| ExchangeDataStatus::Success)
+                .collect();
+            self.calls
+                .lock()
+                .unwrap()
+                .cancels
+                .extend(cancels.iter().map(|cancel| cancel.oid));
+            Ok(ok_response(statuses))
+        }
+
+        async fn update_leverage(
+            &self,
+            _leverage: u32,
+            _coin: &str,
+            _is_cross: bool,
+        ) -> crate::prelude::Result<ExchangeResponseStatus> {
+            if self.reject_leverage {
+                return Ok(ExchangeResponseStatus::Err(
+                    "Cannot switch leverage type with open position.".to_string(),
+                ));
+            }
+            Ok(ExchangeResponseStatus::Ok(ExchangeResponse {
+                response_type: "default".to_string(),
+                data: None,
+            }))
+        }
+
+        fn sz_decimals(&self, coin: &str) -> Option<u32> {
+            (coin == "ETH" || coin == "PURR/USDC").then_some(2)
+        }
+
+        fn spot_tokens(&self, coin: &str) -> Option<(String, String)> {
+            (coin == "PURR/USDC").then(|| ("PURR".to_string(), "USDC".to_string()))
+        }
+    }
+
+    impl MarketDataApi for MockClient {
+        async fn open_orders(
+            &self,
+            address: H160,
+        ) -> crate::prelude::Result<Vec<OpenOrdersResponse>> {
+            let mut calls = self.calls.lock().unwrap();
+            calls.queried_addresses.push(address);
+            if !self.unconfirmed_orders {
+                return Ok(Vec::new());
+            }
+            Ok((1..)
+                .zip(&calls.orders)
+                .map(|(oid, order)| OpenOrdersResponse {
+                    coin: "ETH".to_string(),
+                    limit_px: order.limit_px.to_string(),
+                    oid,
+                    side: if order.is_buy { "B" } else { "A" }.to_string(),
+                    sz: order.sz.to_string(),
+                    timestamp: 0,
+                })
+                .collect())
+        }
+
+        async fn user_state(&self, address: H160) -> crate::prelude::Result<UserStateResponse> {
+            self.calls.lock().unwrap().queried_addresses.push(address);
+            let summary = format!(
+                r#"{{"accountValue":"10000","totalMarginUsed":"{}","totalNtlPos":"0","totalRawUsd":"10000"}}"#,
+                self.margin_used
+            );
+            Ok(serde_json::from_str(&format!(
+                r#"{{"assetPositions":[],"crossMarginSummary":{summary},"marginSummary":{summary},"withdrawable":"{}"}}"#,
+                10_000.0 - self.margin_used
+            ))
+            .unwrap())
+        }
+
+        async fn spot_user_state(
+            &self,
+            address: H160,
+        ) -> crate::prelude::Result<SpotUserStateResponse> {
+            self.calls.lock().unwrap().queried_addresses.push(address);
+            let (base, quote) = self.spot_balances;
+            Ok(serde_json::from_str(&format!(
+                r#"{{"balances":[
+                    {{"coin":"USDC","token":0,"hold":"0.0","total":"{quote}","entryNtl":"0.0"}},
+                    {{"coin":"PURR","token":1,"hold":"0.0","total":"{base}","entryNtl":"0.0"}}
+                ]}}"#
+            ))
+            .unwrap())
+        }
+
+        async fn funding_history(
+            &self,
+            coin: String,
+            start_time: u64,
+            _end_time: Option<u64>,
+        ) -> crate::prelude::Result<Vec<FundingHistoryResponse>> {
+            Ok(vec![FundingHistoryResponse {
+                coin,
+                funding_rate: self.funding_rate.to_string(),
+                premium: "0".to_string(),
+                time: start_time,
+            }])
+        }
+
+        async fn subscribe(
+            &mut self,
+            subscription: Subscription,
+            sender_channel: SubscriptionSender,
+        ) -> crate::prelude::Result<u32> {
+            self.calls
+                .lock()
+                .unwrap()
+                .subscriptions
+                .push((subscription, sender_channel));
+            Ok(0)
+        }
+
+        async fn unsubscribe(&mut self, _subscription_id: u32) -> crate::prelude::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn input() -> Input {
+        Input {
+            asset: "ETH".to_string(),
+            target_liquidity: 1.0,
+            half_spread: 5,
+            max_bps_diff: 10,
+            max_absolute_position_size: 1.5,
+            decimals: 2,
+            leverage: 3,
+            is_cross: true,
+            max_margin_utilization: 0.5,
+            min_liquidation_distance_bps: 1000,
+            funding_skew_hours: 0.0,
+            wallet: "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+                .parse()
+                .unwrap(),
+            vault_address: None,
+        }
+    }
+
+    fn mid(px: &str) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"channel":"allMids","data":{{"mids":{{"ETH":"{px}"}}}}}}"#
+        ))
+        .unwrap()
+    }
+
+    fn fill(oid: u64, is_buy: bool, px: f64, sz: f64) -> Message {
+        Message::User(User {
+            data: UserData {
+                fills: vec![TradeInfo {
+                    coin: "ETH".to_string(),
+                    side: if is_buy { "B" } else { "A" }.to_string(),
+                    px: px.to_string(),
+                    sz: sz.to_string(),
+                    time: 0,
+                    hash: String::new(),
+                    start_position: "0".to_string(),
+                    dir: if is_buy { "Open Long" } else { "Open Short" }.to_string(),
+                    closed_pnl: "0".to_string(),
+                    oid,
+                    cloid: None,
+                    crossed: false,
+                    fee: "0".to_string(),
+                }],
+            },
+        })
+    }
+
+    async fn market_maker(client: &MockClient) -> MarketMaker<MockClient, MockClient> {
+        MarketMaker::with_clients(input(), client.clone(), client.clone())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_vault_account_is_queried() {
+        let client = MockClient::default();
+        let vault_address = H160::from_low_u64_be(1);
+        let mut market_maker = MarketMaker::with_clients(
+            Input {
+                vault_address: Some(vault_address),
+                ..input()
+            },
+            client.clone(),
+            client.clone(),
+        )
+        .await
+        .unwrap();
+        market_maker.process_message(mid("100")).await;
+
+        assert_eq!(market_maker.user_address, vault_address);
+        let queried = client.calls.lock().unwrap().queried_addresses.clone();
+        assert!(!queried.is_empty());
+        assert!(queried.iter().all(|&address| address == vault_address));
+    }
+
+    #[tokio::test]
+    async fn test_journal_restores_pnl_and_records_orders() {
+        let path = std::env::temp_dir()
+            .join(format!("journal-{}", uuid::Uuid::new_v4()))
+            .join("ETH.jsonl");
+        let mut journal = Journal::open(&path).unwrap();
+        journal
+            .append(JournalEvent::OrderAck {
+                oid: 7,
+                is_buy: true,
+                px: 99.0,
+                sz: 1.0,
+            })
+            .unwrap();
+        journal
+            .append(JournalEvent::Fill {
+                oid: 6,
+                is_buy: false,
+                px: 101.0,
+                sz: 1.0,
+                fee: 0.1,
+                closed_pnl: 2.0,
+            })
+            .unwrap();
+        drop(journal);
+
+        let client = MockClient::default();
+        let mut market_maker = market_maker(&client).await;
+        market_maker.restore_from_journal(&path).unwrap();
+        assert!((market_maker.realized_pnl - 2.0).abs() < EPSILON);
+        assert!((market_maker.fees - 0.1).abs() < EPSILON);
+        market_maker.process_message(mid("100")).await;
+
+        // The order that vanished while stopped is closed and the exchange
+        // position replaces the journaled one
+        let state = Journal::replay(&path).unwrap();
+        assert!(!state.open_orders.contains_key(&7));
+        assert!(state.position.abs() < EPSILON);
+        let acked = Journal::read(&path)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| matches!(entry.event, JournalEvent::OrderAck { .. }))
+            .count();
+        assert_eq!(acked, 3);
+        assert_eq!(state.open_orders.len(), 2);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_events_share_the_decision_correlation_id() {
+        let client = MockClient::default();
+        let mut market_maker = market_maker(&client).await;
+        let (sender, mut receiver) = unbounded_channel();
+        market_maker.set_event_sink(sender);
+        market_maker.process_message(mid("100")).await;
+        let bid_oid = market_maker.lower_resting.oid;
+        market_maker
+            .process_message(fill(bid_oid, true, market_maker.lower_resting.price, 0.5))
+            .await;
+
+        let mut records = Vec::new();
+        while let Ok(record) = receiver.try_recv() {
+            records.push(record);
+        }
+        let TradingEvent::QuoteDecision { mid, .. } = records[0].event else {
+            panic!("expected a quote decision, got {:?}", records[0]);
+        };
+        assert!((mid - 100.0).abs() < EPSILON);
+        let decision = records[0].correlation_id;
+        // Both initial quotes belong to the first decision, the requote after
+        // the fill to a new one
+        let acks = records
+            .iter()
+            .filter(|record| matches!(record.event, TradingEvent::OrderAck { .. }))
+            .map(|record| record.correlation_id)
+            .collect::<Vec<_>>();
+        assert_eq!(acks.len(), 3);
+        assert_eq!(acks[..2], [decision, decision]);
+        assert_ne!(acks[2], decision);
+        let fill = records
+            .iter()
+            .find(|record| matches!(record.event, TradingEvent::Fill { .. }))
+            .unwrap();
+        assert_eq!(fill.correlation_id, decision);
+        assert!(records.iter().all(|record| record.asset == "ETH"));
+    }
+
+    #[tokio::test]
+    async fn test_quotes_both_sides_around_mid() {
+        let client = MockClient::default();
+        let mut market_maker = market_maker(&client).await;
+
+        market_maker.process_message(mid("100")).await;
+
+        let orders = client.orders();
+        assert_eq!(orders.len(), 2);
+        let (bid, ask) = (&orders[0], &orders[1]);
+        assert!(bid.is_buy && !ask.is_buy);
+        assert!(bid.limit_px < 100.0 && ask.limit_px > 100.0);
+        assert!((bid.sz - 1.0).abs() < EPSILON && (ask.sz - 1.0).abs() < EPSILON);
+
+        // A small move stays within max_bps_diff, so the quotes are kept
+        market_maker.process_message(mid("100.01")).await;
+        assert_eq!(client.orders().len(), 2);
+        assert!(client.calls.lock().unwrap().cancels.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fills_are_not_dropped_behind_market_data() {
+        let client = MockClient::default();
+        let mut market_maker = market_maker(&client).await;
+        let running = tokio::spawn(async move {
+            market_maker.start().await;
+            market_maker
+        });
+        let subscribed = |count| client.calls.lock().unwrap().subscriptions.len() == count;
+        while !subscribed(3) {
+            tokio::task::yield_now().await;
+        }
+        let sender = |subscription: Subscription| {
+            let calls = client.calls.lock().unwrap();
+            calls
+                .subscriptions
+                .iter()
+                .find(|(subscribed, _)| {
+                    std::mem::discriminant(subscribed) == std::mem::discriminant(&subscription)
+                })
+                .map(|(_, sender)| sender.clone())
+                .unwrap()
+        };
+        let user_events = sender(Subscription::UserEvents { user: H160::zero() });
+        let all_mids = sender(Subscription::AllMids);
+
+        all_mids.send(mid("100")).await.unwrap();
+        while client.orders().len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        // More fills than market data is buffered, while mids keep arriving
+        for _ in 0..100 {
+            user_events.send(fill(0, true, 100.0, 0.01)).await.unwrap();
+            all_mids.send(mid("100")).await.unwrap();
+        }
+        drop((user_events, all_mids));
+        client.calls.lock().unwrap().subscriptions.clear();
+
+        let market_maker = running.await.unwrap();
+        assert!((market_maker.cur_position - 1.0).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_orders_are_looked_up() {
+        let client = MockClient {
+            unconfirmed_orders: true,
+            ..MockClient::default()
+        };
+        let mut market_maker = market_maker(&client).await;
+
+        market_maker.process_message(mid("100")).await;
+
+        assert_eq!(client.orders().len(), 2);
+        assert_eq!(market_maker.lower_resting.oid, 1);
+        assert_eq!(market_maker.upper_resting.oid, 2);
+        assert!((market_maker.lower_resting.position - 1.0).abs() < EPSILON);
+        assert_eq!(market_maker.active_orders.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fill_requotes_remaining_position_capacity() {
+        let client = MockClient::default();
+        let mut market_maker = market_maker(&client).await;
+        market_maker.process_message(mid("100")).await;
+        let bid_oid = market_maker.lower_resting.oid;
+
+        market_maker
+            .process_message(fill(bid_oid, true, market_maker.lower_resting.price, 1.0))
+            .await;
+
+        assert!((market_maker.cur_position - 1.0).abs() < EPSILON);
+        // Only half a lot fits below the maximum position of 1.5
+        let orders = client.orders();
+        assert_eq!(orders.len(), 3);
+        assert!(orders[2].is_buy);
+        assert!((orders[2].sz - 0.5).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_no_new_exposure_at_max_margin_utilization() {
+        let client = MockClient {
+            margin_used: 5_000.0,
+            ..MockClient::default()
+        };
+        let mut market_maker = market_maker(&client).await;
+
+        market_maker.process_message(mid("100")).await;
+
+        assert!(client.orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_config_update_applies_at_next_quote_update() {
+        let client = MockClient::default();
+        let mut market_maker = market_maker(&client).await;
+        let config = MarketConfig {
+            asset: "ETH".to_string(),
+            target_liquidity: 1.0,
+            half_spread: 5,
+            max_bps_diff: 10,
+            max_absolute_position_size: 1.5,
+            decimals: 2,
+            leverage: 3,
+            is_cross: true,
+            max_margin_utilization: 0.5,
+            min_liquidation_distance_bps: 1000,
+            funding_skew_hours: 0.0,
+        };
+        let (sender, receiver) = watch::channel(config.clone());
+        market_maker.set_config_updates(receiver);
+        market_maker.process_message(mid("100")).await;
+        assert_eq!(client.orders().len(), 2);
+
+        // Invalid updates are ignored
+        sender.send_replace(MarketConfig {
+            half_spread: 0,
+            ..config.clone()
+        });
+        market_maker.process_message(mid("100")).await;
+        assert_eq!(market_maker.half_spread, 5);
+        assert_eq!(client.orders().len(), 2);
+
+        sender.send_replace(MarketConfig {
+            half_spread: 50,
+            ..config
+        });
+        market_maker.process_message(mid("100")).await;
+        assert_eq!(market_maker.half_spread, 50);
+        // Both quotes are moved out to the wider spread
+        assert_eq!(client.calls.lock().unwrap().cancels.len(), 2);
+        let orders = client.orders();
+        assert_eq!(orders.len(), 4);
+        assert!(orders[2].limit_px < 99.6 && orders[3].limit_px > 100.4);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_leverage_fails_construction() {
+        let client = MockClient {
+            reject_leverage: true,
+            ..MockClient::default()
+        };
+        let result = MarketMaker::with_clients(input(), client.clone(), client).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_spot_quotes_are_limited_by_token_balances() {
+        // Spot pairs have no leverage to update
+        let client = MockClient {
+            reject_leverage: true,
+            spot_balances: (0.5, 50.0),
+            ..MockClient::default()
+        };
+        let mut market_maker = MarketMaker::with_clients(
+            Input {
+                asset: "PURR/USDC".to_string(),
+                ..input()
+            },
+            client.clone(),
+            client.clone(),
+        )
+        .await
+        .unwrap();
+        assert!((market_maker.cur_position - 0.5).abs() < EPSILON);
+
+        market_maker
+            .process_message(
+                serde_json::from_str(
+                    r#"{"channel":"allMids","data":{"mids":{"PURR/USDC":"100"}}}"#,
+                )
+                .unwrap(),
+            )
+            .await;
+
+        // The bid is limited by the USDC balance and the ask by the PURR
+        // balance, as spot positions can't go short
+        assert_eq!(
+            client.orders(),
+            vec![
+                PlacedOrder {
+                    is_buy: true,
+                    limit_px: 99.96,
+                    sz: 0.5,
+                },
+                PlacedOrder {
+                    is_buy: false,
+                    limit_px: 100.05,
+                    sz: 0.5,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_positive_funding_skews_quotes_down() {
+        let unskewed = MockClient {
+            funding_rate: 0.0001,
+            ..MockClient::default()
+        };
+        market_maker(&unskewed)
+            .await
+            .process_message(mid("100"))
+            .await;
+
+        let client = MockClient {
+            funding_rate: 0.0001,
+            ..MockClient::default()
+        };
+        let mut market_maker = MarketMaker::with_clients(
+            Input {
+                funding_skew_hours: 2.0,
+                ..input()
+            },
+            client.clone(),
+            client.clone(),
+        )
+        .await
+        .unwrap();
+        assert!((market_maker.funding_rate - 0.0001).abs() < EPSILON);
+        market_maker.process_message(mid("100")).await;
+
+        // Shorts receive funding, so both quotes move down by 2 bps to sell
+        // more readily than buy
+        let prices = |client: &MockClient| {
+            client
+                .orders()
+                .iter()
+                .map(|order| (order.is_buy, order.limit_px))
+                .collect::<Vec<_>>()
+        };
+        for (orders, expected) in [
+            (prices(&unskewed), [(true, 99.96), (false, 100.05)]),
+            (prices(&client), [(true, 99.94), (false, 100.03)]),
+        ] {
+            assert_eq!(orders.len(), 2);
+            for ((is_buy, px), (expected_is_buy, expected_px)) in orders.into_iter().zip(expected) {
+                assert_eq!(is_buy, expected_is_buy);
+                assert!((px - expected_px).abs() < EPSILON, "{px} != {expected_px}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expected_funding_pnl_accrues_from_live_rate() {
+        let client = MockClient::default();
+        let mut market_maker = market_maker(&client).await;
+        market_maker.process_message(mid("100")).await;
+        market_maker.cur_position = 2.0;
+
+        market_maker
+            .process_message(
+                serde_json::from_str(
+                    r#"{"channel":"activeAssetCtx","data":{"coin":"ETH","ctx":{
+                        "dayNtlVlm":"1000.0","prevDayPx":"100.0","markPx":"100.0","midPx":"100.0",
+                        "funding":"0.0001","openInterest":"10.0","oraclePx":"100.0"}}}"#,
+                )
+                .unwrap(),
+            )
+            .await;
+        assert!((market_maker.funding_rate - 0.0001).abs() < EPSILON);
+
+        // An hour long, paying 0.01% of 2 * 100 per hour.
+        market_maker.last_funding_accrual = Instant::now().checked_sub(Duration::new(60 * 60, 0));
+        market_maker.accrue_funding();
+        assert!((market_maker.expected_funding_pnl + 0.02).abs() < 1e-4);
+        assert!(market_maker.realized_pnl.abs() < EPSILON);
     }
 }
@@ -1,4 +1,9 @@
-use serde::Deserialize;
+use gxhash::{HashMap, HashMapExt};
+use serde::{Deserialize, Serialize};
+
+/// Offset of spot asset indexes from the index of the pair in the spot
+/// universe.
+pub const SPOT_ASSET_OFFSET: u32 = 10_000;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Meta {
@@ -11,3 +16,176 @@ pub struct AssetMeta {
     pub name: String,
     pub sz_decimals: u32,
 }
+
+/// Market data of a perp. Prices are in USDC, `funding` is the hourly rate
+/// and `open_interest` is in units of the asset.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetCtx {
+    pub day_ntl_vlm: String,
+    pub funding: String,
+    pub mark_px: String,
+    pub mid_px: Option<String>,
+    pub open_interest: String,
+    pub oracle_px: String,
+    pub prev_day_px: String,
+    /// Not included in websocket updates.
+    #[serde(default)]
+    pub premium: Option<String>,
+    /// Prices at which a notional of the impact size would fill on each
+    /// side of the book. Not included in websocket updates.
+    #[serde(default)]
+    pub impact_pxs: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SpotMeta {
+    pub universe: Vec<SpotAssetMeta>,
+    pub tokens: Vec<TokenInfo>,
+}
+
+/// A spot pair. `name` is the coin that identifies it in orders and market
+/// data, e.g. "PURR/USDC" for canonical pairs and "@1" for the others.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotAssetMeta {
+    /// Indexes of the base and quote tokens.
+    pub tokens: [usize; 2],
+    pub name: String,
+    pub index: u32,
+    pub is_canonical: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenInfo {
+    pub name: String,
+    pub sz_decimals: u32,
+    pub wei_decimals: u32,
+    pub index: usize,
+    pub token_id: String,
+    pub is_canonical: bool,
+}
+
+impl TokenInfo {
+    /// The token as identified in spot transfers, e.g.
+    /// "PURR:0xc4bf3f870c0e9465323c0b6ed28096c2".
+    pub fn transfer_id(&self) -> String {
+        format!("{}:{}", self.name, self.token_id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotAssetCtx {
+    pub coin: String,
+    pub day_ntl_vlm: String,
+    pub mark_px: String,
+    pub mid_px: Option<String>,
+    pub prev_day_px: String,
+    pub circulating_supply: String,
+}
+
+impl SpotMeta {
+    /// Asset indexes of the spot pairs, by coin and by "BASE/QUOTE" token
+    /// names.
+    pub fn coin_to_asset(&self) -> HashMap<String, u32> {
+        let mut coin_to_asset = HashMap::new();
+        for asset in &self.universe {
+            let index = SPOT_ASSET_OFFSET + asset.index;
+            coin_to_asset.insert(asset.name.clone(), index);
+            if let Some((base, quote)) = self.tokens(&asset.name) {
+                coin_to_asset
+                    .entry(format!("{}/{}", base.name, quote.name))
+                    .or_insert(index);
+            }
+        }
+        coin_to_asset
+    }
+
+    /// The spot pair identified by `coin`.
+    pub fn pair(&self, coin: &str) -> Option<&SpotAssetMeta> {
+        self.universe.iter().find(|asset| asset.name == coin)
+    }
+
+    /// The coin of the pair trading `base` for `quote`, e.g. "@107" for
+    /// "HYPE" and "USDC".
+    pub fn coin(&self, base: &str, quote: &str) -> Option<&str> {
+        self.universe
+            .iter()
+            .find(|asset| {
+                self.tokens(&asset.name)
+                    .is_some_and(|(b, q)| b.name == base && q.name == quote)
+            })
+            .map(|asset| asset.name.as_str())
+    }
+
+    /// The base and quote tokens of the pair identified by `coin`.
+    pub fn tokens(&self, coin: &str) -> Option<(&TokenInfo, &TokenInfo)> {
+        let [base, quote] = self.pair(coin)?.tokens;
+        Some((self.token(base)?, self.token(quote)?))
+    }
+
+    fn token(&self, index: usize) -> Option<&TokenInfo> {
+        self.tokens.iter().find(|token| token.index == index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPOT_META: &str = r#"{
+        "universe": [
+            {"tokens": [1, 0], "name": "PURR/USDC", "index": 0, "isCanonical": true},
+            {"tokens": [2, 0], "name": "@1", "index": 1, "isCanonical": false}
+        ],
+        "tokens": [
+            {"name": "USDC", "szDecimals": 8, "weiDecimals": 8, "index": 0, "tokenId": "0x6d1e7cde53ba9467b783cb7c530ce054", "isCanonical": true, "evmContract": null, "fullName": null},
+            {"name": "PURR", "szDecimals": 0, "weiDecimals": 5, "index": 1, "tokenId": "0xc4bf3f870c0e9465323c0b6ed28096c2", "isCanonical": true, "evmContract": null, "fullName": null},
+            {"name": "HFUN", "szDecimals": 2, "weiDecimals": 8, "index": 2, "tokenId": "0xbaf265ef389da684513d98d68edf4eae", "isCanonical": false, "evmContract": null, "fullName": null}
+        ]
+    }"#;
+
+    #[test]
+    fn test_asset_contexts() {
+        let (meta, asset_ctxs): (Meta, Vec<AssetCtx>) = serde_json::from_str(
+            r#"[
+                {"universe": [{"name": "BTC", "szDecimals": 5, "maxLeverage": 50}]},
+                [{
+                    "dayNtlVlm": "1169046.29406",
+                    "funding": "0.0000125",
+                    "impactPxs": ["14.3047", "14.3444"],
+                    "markPx": "14.3161",
+                    "midPx": "14.314",
+                    "openInterest": "688.11",
+                    "oraclePx": "14.32",
+                    "premium": "0.00031774",
+                    "prevDayPx": "15.322"
+                }]
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(meta.universe[0].name, "BTC");
+        assert_eq!(asset_ctxs[0].funding, "0.0000125");
+        assert_eq!(asset_ctxs[0].impact_pxs.as_ref().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_spot_asset_indexes() {
+        let spot_meta: SpotMeta = serde_json::from_str(SPOT_META).unwrap();
+        let coin_to_asset = spot_meta.coin_to_asset();
+        assert_eq!(coin_to_asset.get("PURR/USDC"), Some(&10_000));
+        assert_eq!(coin_to_asset.get("@1"), Some(&10_001));
+        assert_eq!(coin_to_asset.get("HFUN/USDC"), Some(&10_001));
+
+        assert_eq!(spot_meta.coin("HFUN", "USDC"), Some("@1"));
+        let (base, quote) = spot_meta.tokens("@1").unwrap();
+        assert_eq!((base.name.as_str(), quote.name.as_str()), ("HFUN", "USDC"));
+        assert_eq!(
+            base.transfer_id(),
+            "HFUN:0xbaf265ef389da684513d98d68edf4eae"
+        );
+        assert!(spot_meta.tokens("ETH").is_none());
+    }
+}
@@ -0,0 +1,521 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use lazy_static::lazy_static;
+use prometheus::{
+    exponential_buckets, CounterVec, Encoder, Gauge, GaugeVec, HistogramOpts, HistogramTimer,
+    HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use tracing::info;
+
+use crate::{prelude::*, Error};
+
+struct Metrics {
+    registry: Registry,
+    position: GaugeVec,
+    quoted_spread_bps: GaugeVec,
+    resting_size: GaugeVec,
+    fills: IntCounterVec,
+    fill_size: CounterVec,
+    fill_volume: CounterVec,
+    realized_pnl: GaugeVec,
+    fees: GaugeVec,
+    unrealized_pnl: GaugeVec,
+    funding_rate: GaugeVec,
+    expected_funding_pnl: GaugeVec,
+    orders: IntCounterVec,
+    cancels: IntCounterVec,
+    rejections: IntCounterVec,
+    rest_latency: HistogramVec,
+    stage_latency: HistogramVec,
+    ws_messages: IntCounterVec,
+    ws_dropped: IntCounterVec,
+    ws_connects: IntCounter,
+    ws_disconnects: IntCounter,
+    ws_reconnects: IntCounter,
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new();
+        let gauge = |name: &str, help: &str, labels: &[&str]| -> prometheus::Result<GaugeVec> {
+            let gauge = GaugeVec::new(Opts::new(name, help), labels)?;
+            registry.register(Box::new(gauge.clone()))?;
+            Ok(gauge)
+        };
+        let counter =
+            |name: &str, help: &str, labels: &[&str]| -> prometheus::Result<IntCounterVec> {
+                let counter = IntCounterVec::new(Opts::new(name, help), labels)?;
+                registry.register(Box::new(counter.clone()))?;
+                Ok(counter)
+            };
+
+        let position = gauge(
+            "market_maker_position",
+            "Current position, negative when short",
+            &["asset"],
+        )?;
+        let quoted_spread_bps = gauge(
+            "market_maker_quoted_spread_bps",
+            "Distance between the resting bid and ask in basis points of the mid price",
+            &["asset"],
+        )?;
+        let resting_size = gauge(
+            "market_maker_resting_size",
+            "Size of the resting order",
+            &["asset", "side"],
+        )?;
+        let fills = counter("market_maker_fills_total", "Fills", &["asset", "side"])?;
+        let fill_size = CounterVec::new(
+            Opts::new("market_maker_fill_size_total", "Filled size"),
+            &["asset", "side"],
+        )?;
+        registry.register(Box::new(fill_size.clone()))?;
+        let fill_volume = CounterVec::new(
+            Opts::new(
+                "market_maker_fill_volume_usd_total",
+                "Filled notional value in USD",
+            ),
+            &["asset", "side"],
+        )?;
+        registry.register(Box::new(fill_volume.clone()))?;
+        let realized_pnl = gauge(
+            "market_maker_realized_pnl",
+            "Closed PnL of all fills, before fees",
+            &["asset"],
+        )?;
+        let fees = gauge("market_maker_fees", "Fees paid for all fills", &["asset"])?;
+        let unrealized_pnl = gauge(
+            "market_maker_unrealized_pnl",
+            "Unrealized PnL of the position as of the last account refresh",
+            &["asset"],
+        )?;
+        let funding_rate = gauge(
+            "market_maker_funding_rate",
+            "Predicted hourly funding rate, positive when longs pay shorts",
+            &["asset"],
+        )?;
+        let expected_funding_pnl = gauge(
+            "market_maker_expected_funding_pnl",
+            "Funding the position is expected to have earned at the predicted rates, separate from the trading PnL",
+            &["asset"],
+        )?;
+        let orders = counter("market_maker_orders_total", "Orders sent", &["asset"])?;
+        let cancels = counter("market_maker_cancels_total", "Cancels sent", &["asset"])?;
+        let rejections = counter(
+            "market_maker_rejections_total",
+            "Orders that weren't placed, by reason",
+            &["asset", "reason"],
+        )?;
+
+        let rest_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "hyperliquid_rest_request_duration_seconds",
+                "Duration of REST requests until the response is read",
+            )
+            .buckets(exponential_buckets(0.005, 2.0, 12)?),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(rest_latency.clone()))?;
+        let stage_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "hyperliquid_stage_duration_seconds",
+                "Duration of the stages between receiving market data and an order being acknowledged",
+            )
+            .buckets(exponential_buckets(0.000_01, 2.0, 20)?),
+            &["stage"],
+        )?;
+        registry.register(Box::new(stage_latency.clone()))?;
+        let ws_messages = counter(
+            "hyperliquid_ws_messages_total",
+            "Websocket messages received",
+            &["channel"],
+        )?;
+        let ws_dropped = counter(
+            "hyperliquid_ws_dropped_messages_total",
+            "Websocket messages dropped or replaced by a newer one because the subscriber's channel was full",
+            &["channel"],
+        )?;
+        let ws_connects = IntCounter::new(
+            "hyperliquid_ws_connects_total",
+            "Websocket connections opened",
+        )?;
+        registry.register(Box::new(ws_connects.clone()))?;
+        let ws_disconnects = IntCounter::new(
+            "hyperliquid_ws_disconnects_total",
+            "Websocket connections that ended",
+        )?;
+        registry.register(Box::new(ws_disconnects.clone()))?;
+        let ws_reconnects = IntCounter::new(
+            "hyperliquid_ws_reconnects_total",
+            "Websocket connections reopened and resubscribed after ending",
+        )?;
+        registry.register(Box::new(ws_reconnects.clone()))?;
+
+        Ok(Self {
+            registry,
+            position,
+            quoted_spread_bps,
+            resting_size,
+            fills,
+            fill_size,
+            fill_volume,
+            realized_pnl,
+            fees,
+            unrealized_pnl,
+            funding_rate,
+            expected_funding_pnl,
+            orders,
+            cancels,
+            rejections,
+            rest_latency,
+            stage_latency,
+            ws_messages,
+            ws_dropped,
+            ws_connects,
+            ws_disconnects,
+            ws_reconnects,
+        })
+    }
+}
+
+lazy_static! {
+    static ref METRICS: Metrics = Metrics::new().expect("metrics are valid");
+    static ref WS_DISPATCHES: Mutex<HashMap<&'static str, WsDispatch>> = Mutex::new(HashMap::new());
+}
+
+/// Stages of the path from a websocket message to an acknowledged order,
+/// used as the `stage` label of the stage latency histogram.
+pub(crate) mod stage {
+    /// Parsing a websocket message and handing it to the subscribers.
+    pub(crate) const WS_PARSE: &str = "ws_parse";
+    /// Waiting in the subscriber channel until the market maker receives
+    /// the message.
+    pub(crate) const CHANNEL_HANDOFF: &str = "channel_handoff";
+    /// Computing quotes and sending the resulting orders and cancels.
+    pub(crate) const POTENTIALLY_UPDATE: &str = "potentially_update";
+    /// Signing an action.
+    pub(crate) const SIGN: &str = "sign";
+    /// From receiving the mid price to the order being acknowledged.
+    pub(crate) const TICK_TO_TRADE: &str = "tick_to_trade";
+}
+
+/// When the latest message of a websocket channel was received and handed
+/// to the subscribers.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct WsDispatch {
+    pub(crate) received: Instant,
+    pub(crate) dispatched: Instant,
+}
+
+fn side(is_buy: bool) -> &'static str {
+    if is_buy {
+        "bid"
+    } else {
+        "ask"
+    }
+}
+
+/// Reason label of an order the exchange rejected with `error`, grouping
+/// the error messages into a few stable categories.
+fn rejection_reason(error: &str) -> &'static str {
+    let error = error.to_lowercase();
+    if error.contains("post only") || error.contains("time in force") {
+        "post_only"
+    } else if error.contains("margin") {
+        "insufficient_margin"
+    } else if error.contains("rate limit") || error.contains("too many") {
+        "rate_limit"
+    } else if error.contains("minimum value") {
+        "min_value"
+    } else if error.contains("tick") || error.contains("price") {
+        "invalid_price"
+    } else if error.contains("reduce only") {
+        "reduce_only"
+    } else {
+        "other"
+    }
+}
+
+/// Metrics of the market maker quoting one asset.
+#[derive(Clone)]
+pub struct AssetMetrics {
+    asset: String,
+    position: Gauge,
+    quoted_spread_bps: Gauge,
+    realized_pnl: Gauge,
+    fees: Gauge,
+    unrealized_pnl: Gauge,
+    funding_rate: Gauge,
+    expected_funding_pnl: Gauge,
+    orders: IntCounter,
+    cancels: IntCounter,
+}
+
+impl AssetMetrics {
+    pub fn new(asset: &str) -> Self {
+        let metrics = &*METRICS;
+        Self {
+            asset: asset.to_string(),
+            position: metrics.position.with_label_values(&[asset]),
+            quoted_spread_bps: metrics.quoted_spread_bps.with_label_values(&[asset]),
+            realized_pnl: metrics.realized_pnl.with_label_values(&[asset]),
+            fees: metrics.fees.with_label_values(&[asset]),
+            unrealized_pnl: metrics.unrealized_pnl.with_label_values(&[asset]),
+            funding_rate: metrics.funding_rate.with_label_values(&[asset]),
+            expected_funding_pnl: metrics.expected_funding_pnl.with_label_values(&[asset]),
+            orders: metrics.orders.with_label_values(&[asset]),
+            cancels: metrics.cancels.with_label_values(&[asset]),
+        }
+    }
+
+    pub fn set_position(&self, position: f64) {
+        self.position.set(position);
+    }
+
+    pub fn set_quoted_spread_bps(&self, spread_bps: f64) {
+        self.quoted_spread_bps.set(spread_bps);
+    }
+
+    pub fn set_resting_size(&self, is_buy: bool, size: f64) {
+        METRICS
+            .resting_size
+            .with_label_values(&[&self.asset, side(is_buy)])
+            .set(size);
+    }
+
+    pub fn record_fill(&self, is_buy: bool, size: f64, price: f64) {
+        let labels = [self.asset.as_str(), side(is_buy)];
+        METRICS.fills.with_label_values(&labels).inc();
+        METRICS.fill_size.with_label_values(&labels).inc_by(size);
+        METRICS
+            .fill_volume
+            .with_label_values(&labels)
+            .inc_by(size * price);
+    }
+
+    pub fn set_pnl(&self, realized_pnl: f64, fees: f64, unrealized_pnl: f64) {
+        self.realized_pnl.set(realized_pnl);
+        self.fees.set(fees);
+        self.unrealized_pnl.set(unrealized_pnl);
+    }
+
+    pub fn set_funding(&self, funding_rate: f64, expected_funding_pnl: f64) {
+        self.funding_rate.set(funding_rate);
+        self.expected_funding_pnl.set(expected_funding_pnl);
+    }
+
+    pub fn record_order(&self) {
+        self.orders.inc();
+    }
+
+    pub fn record_cancel(&self) {
+        self.cancels.inc();
+    }
+
+    /// Counts an order the exchange rejected with `error`.
+    pub fn record_rejection(&self, error: &str) {
+        self.record_failure(rejection_reason(error));
+    }
+
+    /// Counts an order whose request failed before the exchange handled it.
+    pub fn record_request_failure(&self) {
+        self.record_failure("request_failed");
+    }
+
+    fn record_failure(&self, reason: &str) {
+        METRICS
+            .rejections
+            .with_label_values(&[&self.asset, reason])
+            .inc();
+    }
+}
+
+/// Starts timing `stage`, which is recorded when the timer is dropped.
+pub(crate) fn stage_timer(stage: &str) -> HistogramTimer {
+    METRICS
+        .stage_latency
+        .with_label_values(&[stage])
+        .start_timer()
+}
+
+pub(crate) fn observe_stage(stage: &str, duration: Duration) {
+    METRICS
+        .stage_latency
+        .with_label_values(&[stage])
+        .observe(duration.as_secs_f64());
+}
+
+/// Records that a message of `channel` received at `received` was handed to
+/// the subscribers.
+pub(crate) fn record_ws_dispatch(channel: &'static str, received: Instant) {
+    let dispatched = Instant::now();
+    observe_stage(stage::WS_PARSE, dispatched - received);
+    WS_DISPATCHES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(
+            channel,
+            WsDispatch {
+                received,
+                dispatched,
+            },
+        );
+}
+
+/// The latest dispatch of a message of `channel`. With messages arriving
+/// faster than they are processed this is a later message than the one
+/// being processed, so latencies derived from it are a lower bound.
+pub(crate) fn last_ws_dispatch(channel: &str) -> Option<WsDispatch> {
+    WS_DISPATCHES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(channel)
+        .copied()
+}
+
+/// Starts timing a REST request to `endpoint`, which is recorded when the
+/// timer is dropped.
+pub(crate) fn rest_request_timer(endpoint: &str) -> HistogramTimer {
+    METRICS
+        .rest_latency
+        .with_label_values(&[endpoint])
+        .start_timer()
+}
+
+pub(crate) fn record_ws_message(channel: &str) {
+    METRICS.ws_messages.with_label_values(&[channel]).inc();
+}
+
+pub(crate) fn record_ws_dropped(channel: &str) {
+    METRICS.ws_dropped.with_label_values(&[channel]).inc();
+}
+
+pub(crate) fn record_ws_connect() {
+    METRICS.ws_connects.inc();
+}
+
+pub(crate) fn record_ws_disconnect() {
+    METRICS.ws_disconnects.inc();
+}
+
+pub(crate) fn record_ws_reconnect() {
+    METRICS.ws_reconnects.inc();
+}
+
+/// All metrics in the Prometheus text format.
+pub fn gather_metrics() -> Result<String> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&METRICS.registry.gather(), &mut buffer)
+        .map_err(|e| Error::GenericParse(e.to_string()))?;
+    String::from_utf8(buffer).map_err(|e| Error::GenericParse(e.to_string()))
+}
+
+async fn handle(request: Request<Body>) -> std::result::Result<Response<Body>, Infallible> {
+    if request.method() != Method::GET || request.uri().path() != "/metrics" {
+        let mut response = Response::new(Body::from("Not found\n"));
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return Ok(response);
+    }
+    let mut response = match gather_metrics() {
+        Ok(metrics) => Response::new(Body::from(metrics)),
+        Err(e) => {
+            let mut response = Response::new(Body::from(e.to_string()));
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            return Ok(response);
+        }
+    };
+    if let Ok(content_type) = TextEncoder::new().format_type().parse() {
+        response.headers_mut().insert(CONTENT_TYPE, content_type);
+    }
+    Ok(response)
+}
+
+/// Serves the metrics at `http://<addr>/metrics` until the server fails.
+pub async fn serve_metrics(addr: SocketAddr) -> Result<()> {
+    let server = Server::try_bind(&addr)
+        .map_err(|e| Error::Io(format!("metrics server on {addr}: {e}")))?
+        .serve(make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(handle))
+        }));
+    info!("Serving metrics on http://{}/metrics", server.local_addr());
+    server
+        .await
+        .map_err(|e| Error::Io(format!("metrics server on {addr}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejection_reasons() {
+        assert_eq!(
+            rejection_reason("Post only order would have immediately matched"),
+            "post_only"
+        );
+        assert_eq!(
+            rejection_reason("Insufficient margin to place order."),
+            "insufficient_margin"
+        );
+        assert_eq!(
+            rejection_reason("Order must have minimum value of $10."),
+            "min_value"
+        );
+        assert_eq!(rejection_reason("Something new"), "other");
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let metrics = AssetMetrics::new("METRICS_TEST");
+        metrics.record_fill(true, 2.0, 10.0);
+        metrics.record_rejection("Insufficient margin to place order.");
+        metrics.set_position(2.0);
+
+        let response = handle(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains(r#"market_maker_fills_total{asset="METRICS_TEST",side="bid"} 1"#));
+        assert!(body
+            .contains(r#"market_maker_fill_volume_usd_total{asset="METRICS_TEST",side="bid"} 20"#));
+        assert!(body.contains(
+            r#"market_maker_rejections_total{asset="METRICS_TEST",reason="insufficient_margin"} 1"#
+        ));
+        assert!(body.contains(r#"market_maker_position{asset="METRICS_TEST"} 2"#));
+
+        let response = handle(Request::get("/other").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_stage_latency() -> Result<()> {
+        let received = Instant::now();
+        record_ws_dispatch("stageTest", received);
+        let dispatch = last_ws_dispatch("stageTest").unwrap();
+        assert_eq!(dispatch.received, received);
+        assert!(dispatch.dispatched >= received);
+        assert!(last_ws_dispatch("otherStageTest").is_none());
+
+        stage_timer(stage::SIGN).observe_duration();
+        let body = gather_metrics()?;
+        assert!(body.contains(r#"hyperliquid_stage_duration_seconds_count{stage="ws_parse"}"#));
+        assert!(body.contains(r#"hyperliquid_stage_duration_seconds_count{stage="sign"}"#));
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{prelude::*, Error};
+use crate::{metrics::rest_request_timer, prelude::*, Error};
 use reqwest::{Client, Response};
 use serde::Deserialize;
 
@@ -16,7 +16,7 @@ pub struct HttpClient {
 
 async fn parse_response(response: Response) -> Result<String> {
     let status_code = response.status().as_u16();
-    let headers = response.headers().clone();
+    let headers = Box::new(response.headers().clone());
     let text = response
         .text()
         .await
@@ -53,7 +53,9 @@ async fn parse_response(response: Response) -> Result<String> {
 }
 
 impl HttpClient {
+    #[tracing::instrument(name = "rest_request", skip(self, data), fields(base_url = %self.base_url))]
     pub async fn post(&self, url_path: &'static str, data: String) -> Result<String> {
+        let _timer = rest_request_timer(url_path);
         let full_url = format!("{}{url_path}", self.base_url);
         let request = self
             .client
@@ -8,10 +8,16 @@ use ethers::{
     types::{transaction::eip712::Eip712, Signature, H256, U256},
 };
 
-use crate::{prelude::*, proxy_digest::Sha256Proxy, signature::agent::l1, Error};
+use crate::{
+    metrics::{stage, stage_timer},
+    prelude::*,
+    proxy_digest::Sha256Proxy,
+    signature::agent::l1,
+    ActionSigner, Error,
+};
 
-pub(crate) fn sign_l1_action(
-    wallet: &LocalWallet,
+pub(crate) async fn sign_l1_action(
+    signer: &dyn ActionSigner,
     connection_id: H256,
     is_mainnet: bool,
 ) -> Result<Signature> {
@@ -21,19 +27,25 @@ pub(crate) fn sign_l1_action(
             source,
             connection_id,
         },
-        wallet,
+        signer,
     )
+    .await
 }
 
-pub(crate) fn sign_typed_data<T: Eip712>(payload: &T, wallet: &LocalWallet) -> Result<Signature> {
+#[tracing::instrument(level = "debug", skip_all)]
+pub(crate) async fn sign_typed_data<T: Eip712>(
+    payload: &T,
+    signer: &dyn ActionSigner,
+) -> Result<Signature> {
+    let _timer = stage_timer(stage::SIGN);
     let encoded = payload
         .encode_eip712()
         .map_err(|e| Error::Eip712(e.to_string()))?;
 
-    Ok(sign_hash(H256::from(encoded), wallet))
+    signer.sign_hash(H256::from(encoded)).await
 }
 
-fn sign_hash(hash: H256, wallet: &LocalWallet) -> Signature {
+pub(crate) fn sign_hash(hash: H256, wallet: &LocalWallet) -> Signature {
     let recoverable_sig: recoverable::Signature =
         wallet.signer().sign_digest(Sha256Proxy::from(hash));
 
@@ -60,8 +72,8 @@ mod tests {
             .map_err(|e| Error::Wallet(e.to_string()))
     }
 
-    #[test]
-    fn test_sign_l1_action() -> Result<()> {
+    #[tokio::test]
+    async fn test_sign_l1_action() -> Result<()> {
         let wallet = get_wallet()?;
         let connection_id =
             H256::from_str("0xde6c4037798a4434ca03cd05f00e3b803126221375cd1e7eaaaf041768be06eb")
@@ -69,19 +81,67 @@ mod tests {
 
         let expected_mainnet_sig = "fa8a41f6a3fa728206df80801a83bcbfbab08649cd34d9c0bfba7c7b2f99340f53a00226604567b98a1492803190d65a201d6805e5831b7044f17fd530aec7841c";
         assert_eq!(
-            sign_l1_action(&wallet, connection_id, true)?.to_string(),
+            sign_l1_action(&wallet, connection_id, true)
+                .await?
+                .to_string(),
             expected_mainnet_sig
         );
         let expected_testnet_sig = "1713c0fc661b792a50e8ffdd59b637b1ed172d9a3aa4d801d9d88646710fb74b33959f4d075a7ccbec9f2374a6da21ffa4448d58d0413a0d335775f680a881431c";
         assert_eq!(
-            sign_l1_action(&wallet, connection_id, false)?.to_string(),
+            sign_l1_action(&wallet, connection_id, false)
+                .await?
+                .to_string(),
             expected_testnet_sig
         );
         Ok(())
     }
 
-    #[test]
-    fn test_sign_usd_transfer_action() -> Result<()> {
+    /// Signer that holds its wallet elsewhere, like a signing daemon would.
+    struct RemoteSigner {
+        wallet: LocalWallet,
+        requests: std::sync::Mutex<Vec<H256>>,
+    }
+
+    impl ActionSigner for RemoteSigner {
+        fn address(&self) -> ethers::types::H160 {
+            ActionSigner::address(&self.wallet)
+        }
+
+        fn sign_hash(&self, hash: H256) -> futures_util::future::BoxFuture<'_, Result<Signature>> {
+            if let Ok(mut requests) = self.requests.lock() {
+                requests.push(hash);
+            }
+            Box::pin(async move { ActionSigner::sign_hash(&self.wallet, hash).await })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_l1_action_with_custom_signer() -> Result<()> {
+        let signer = RemoteSigner {
+            wallet: get_wallet()?,
+            requests: std::sync::Mutex::new(Vec::new()),
+        };
+        let connection_id =
+            H256::from_str("0xde6c4037798a4434ca03cd05f00e3b803126221375cd1e7eaaaf041768be06eb")
+                .map_err(|e| Error::GenericParse(e.to_string()))?;
+
+        assert_eq!(
+            sign_l1_action(&signer, connection_id, true).await?,
+            sign_l1_action(&get_wallet()?, connection_id, true).await?
+        );
+        assert_eq!(
+            signer
+                .requests
+                .lock()
+                .map_err(|e| Error::Wallet(e.to_string()))?
+                .len(),
+            1
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sign_usd_transfer_action() -> Result<()> {
         let wallet = get_wallet()?;
 
         let usd_send = UsdSend {
@@ -94,7 +154,7 @@ mod tests {
 
         let expected_sig = "214d507bbdaebba52fa60928f904a8b2df73673e3baba6133d66fe846c7ef70451e82453a6d8db124e7ed6e60fa00d4b7c46e4d96cb2bd61fd81b6e8953cc9d21b";
         assert_eq!(
-            sign_typed_data(&usd_send, &wallet)?.to_string(),
+            sign_typed_data(&usd_send, &wallet).await?.to_string(),
             expected_sig
         );
         Ok(())
@@ -1,5 +1,8 @@
 pub(crate) mod agent;
 mod create_signature;
+mod signer;
+#[allow(dead_code)]
 pub(crate) mod usdc_transfer;
 
-pub(crate) use create_signature::{sign_l1_action, sign_typed_data};
+pub(crate) use create_signature::{sign_hash, sign_l1_action, sign_typed_data};
+pub use signer::ActionSigner;
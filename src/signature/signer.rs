@@ -0,0 +1,36 @@
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Signature, H160, H256},
+};
+use futures_util::future::{self, BoxFuture};
+
+use crate::{prelude::*, signature::create_signature::sign_hash};
+
+/// Signs the hashes of exchange actions.
+///
+/// Implemented by `LocalWallet`. Implementing it for a client of a remote
+/// signer, e.g. a local signing daemon, keeps the private key out of this
+/// process.
+///
+/// Unlike the `MarketDataApi` and `ExchangeApi` seams, `sign_hash` returns a
+/// boxed future rather than `impl Future`, because `ExchangeClient` holds its
+/// signer as `Arc<dyn ActionSigner>` and traits returning `impl Trait` can't
+/// be made into trait objects.
+pub trait ActionSigner: Send + Sync {
+    /// Address of the key that signs.
+    fn address(&self) -> H160;
+
+    /// Signs an EIP-712 hash, returning the signature with `v` set to 27 or
+    /// 28.
+    fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<Signature>>;
+}
+
+impl ActionSigner for LocalWallet {
+    fn address(&self) -> H160 {
+        Signer::address(self)
+    }
+
+    fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<Signature>> {
+        Box::pin(future::ready(Ok(sign_hash(hash, self))))
+    }
+}
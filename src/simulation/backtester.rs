@@ -0,0 +1,334 @@
+#![warn(clippy::all, clippy::nursery, clippy::pedantic)]
+
+use std::fmt;
+
+use crate::{
+    simulation::{RecordedMessage, SimulatedExchange, SimulatedFill, SimulationConfig},
+    Input, MarketMaker, Message, User, UserData, EPSILON,
+};
+
+const MS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    pub simulation: SimulationConfig,
+    /// Time in milliseconds after a fill at which the mid price is compared
+    /// to the fill price to measure adverse selection.
+    pub markout_horizon_ms: u64,
+    /// Interval in milliseconds at which the running profit is sampled for
+    /// the Sharpe ratio.
+    pub sample_interval_ms: u64,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            simulation: SimulationConfig::default(),
+            markout_horizon_ms: 10_000,
+            sample_interval_ms: 60_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BacktestReport {
+    /// Profit after fees, with the final position marked at the last mid.
+    pub pnl: f64,
+    pub realized_pnl: f64,
+    pub fees: f64,
+    pub volume: f64,
+    pub fills: usize,
+    pub orders_placed: u64,
+    pub orders_filled: u64,
+    /// Share of placed orders that received at least one fill.
+    pub fill_rate: f64,
+    pub final_position: f64,
+    pub max_abs_position: f64,
+    pub average_abs_position: f64,
+    /// Average move of the mid against our fills after the markout horizon.
+    pub adverse_selection_bps: f64,
+    /// Annualized Sharpe ratio of the sampled profit changes.
+    pub sharpe_ratio: f64,
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "PnL: {:.4} (realized {:.4}, fees {:.4})",
+            self.pnl, self.realized_pnl, self.fees
+        )?;
+        writeln!(f, "Volume: {:.4} over {} fills", self.volume, self.fills)?;
+        writeln!(
+            f,
+            "Orders: {} placed, {} filled, fill rate {:.2}%",
+            self.orders_placed,
+            self.orders_filled,
+            self.fill_rate * 100.0
+        )?;
+        writeln!(
+            f,
+            "Position: final {}, max abs {}, average abs {:.4}",
+            self.final_position, self.max_abs_position, self.average_abs_position
+        )?;
+        writeln!(
+            f,
+            "Adverse selection: {:.2} bps",
+            self.adverse_selection_bps
+        )?;
+        write!(f, "Sharpe ratio: {:.2}", self.sharpe_ratio)
+    }
+}
+
+/// Replays recorded market data through a `MarketMaker` that trades on a
+/// `SimulatedExchange`.
+pub struct Backtester {
+    pub market_maker: MarketMaker<SimulatedExchange, SimulatedExchange>,
+    exchange: SimulatedExchange,
+    config: BacktestConfig,
+    mids: Vec<(u64, f64)>,
+    pnl_samples: Vec<f64>,
+    position_samples: Vec<f64>,
+    next_sample: Option<u64>,
+}
+
+impl Backtester {
+    /// # Errors
+    ///
+    /// Returns `Err` if the market maker can't be created on the simulated
+    /// exchange.
+    pub async fn new(
+        input: Input,
+        config: BacktestConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let exchange = SimulatedExchange::new(input.asset.clone(), config.simulation.clone());
+        let market_maker =
+            MarketMaker::with_clients(input, exchange.clone(), exchange.clone()).await?;
+        Ok(Self {
+            market_maker,
+            exchange,
+            config,
+            mids: Vec::new(),
+            pnl_samples: Vec::new(),
+            position_samples: Vec::new(),
+            next_sample: None,
+        })
+    }
+
+    #[must_use]
+    pub const fn exchange(&self) -> &SimulatedExchange {
+        &self.exchange
+    }
+
+    /// Replays all messages in order and reports the results.
+    pub async fn run(
+        &mut self,
+        messages: impl IntoIterator<Item = RecordedMessage>,
+    ) -> BacktestReport {
+        for message in messages {
+            self.step(message).await;
+        }
+        self.report()
+    }
+
+    /// Replays a single message. Book and trade updates drive the simulated
+    /// exchange, mids and resulting fills go to the market maker.
+    pub async fn step(&mut self, recorded: RecordedMessage) {
+        let RecordedMessage { time, message } = recorded;
+        self.exchange.advance_to(time);
+
+        match message {
+            Message::L2Book(l2_book) => self.exchange.on_l2_book(&l2_book.data),
+            Message::Trades(trades) => self.exchange.on_trades(&trades.data),
+            Message::AllMids(all_mids) => {
+                let mid = all_mids
+                    .data
+                    .mids
+                    .get(self.exchange.asset())
+                    .and_then(|mid| mid.parse::<f64>().ok());
+                if let Some(mid) = mid {
+                    self.exchange.on_mid(mid);
+                    self.mids.push((time, mid));
+                    self.market_maker
+                        .process_message(Message::AllMids(all_mids))
+                        .await;
+                    self.sample(time);
+                }
+            }
+            _ => {}
+        }
+
+        let fills = self.exchange.take_fills();
+        if !fills.is_empty() {
+            self.market_maker
+                .process_message(Message::User(User {
+                    data: UserData { fills },
+                }))
+                .await;
+        }
+        // The market maker tracks its orders through the exchange responses
+        let _ = self.exchange.take_order_updates();
+    }
+
+    fn sample(&mut self, time: u64) {
+        let next_sample = *self.next_sample.get_or_insert(time);
+        if time < next_sample {
+            return;
+        }
+        self.pnl_samples.push(self.exchange.pnl());
+        self.position_samples
+            .push(self.exchange.account().position.abs());
+        let interval = self.config.sample_interval_ms.max(1);
+        self.next_sample =
+            Some(next_sample + (time - next_sample) / interval * interval + interval);
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn report(&self) -> BacktestReport {
+        let account = self.exchange.account();
+        let fills = self.exchange.fills();
+
+        let mut position: f64 = 0.0;
+        let mut max_abs_position: f64 = 0.0;
+        for fill in &fills {
+            position += if fill.is_buy { fill.sz } else { -fill.sz };
+            max_abs_position = max_abs_position.max(position.abs());
+        }
+
+        BacktestReport {
+            pnl: self.exchange.pnl(),
+            realized_pnl: account.realized_pnl,
+            fees: account.fees,
+            volume: account.volume,
+            fills: fills.len(),
+            orders_placed: account.orders_placed,
+            orders_filled: account.orders_filled,
+            fill_rate: if account.orders_placed == 0 {
+                0.0
+            } else {
+                account.orders_filled as f64 / account.orders_placed as f64
+            },
+            final_position: account.position,
+            max_abs_position,
+            average_abs_position: mean(&self.position_samples),
+            adverse_selection_bps: self.adverse_selection_bps(&fills),
+            sharpe_ratio: self.sharpe_ratio(),
+        }
+    }
+
+    fn adverse_selection_bps(&self, fills: &[SimulatedFill]) -> f64 {
+        let markouts: Vec<f64> = fills
+            .iter()
+            .filter_map(|fill| {
+                let horizon = fill.time + self.config.markout_horizon_ms;
+                let index = self.mids.partition_point(|&(time, _)| time < horizon);
+                let &(_, mid_after) = self.mids.get(index)?;
+                let side = if fill.is_buy { 1.0 } else { -1.0 };
+                // Positive when the mid moved against the side we traded
+                Some(side * (fill.px - mid_after) / fill.px * 10_000.0)
+            })
+            .collect();
+        mean(&markouts)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn sharpe_ratio(&self) -> f64 {
+        let changes: Vec<f64> = self
+            .pnl_samples
+            .windows(2)
+            .map(|window| window[1] - window[0])
+            .collect();
+        if changes.len() < 2 {
+            return 0.0;
+        }
+        let average = mean(&changes);
+        let variance = changes
+            .iter()
+            .map(|change| (change - average).powi(2))
+            .sum::<f64>()
+            / (changes.len() - 1) as f64;
+        let std_dev = variance.sqrt();
+        if std_dev < EPSILON {
+            return 0.0;
+        }
+        let periods_per_year = MS_PER_YEAR / self.config.sample_interval_ms.max(1) as f64;
+        average / std_dev * periods_per_year.sqrt()
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::LocalWallet;
+
+    fn recorded(line: &str) -> RecordedMessage {
+        serde_json::from_str(line).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_backtest_fills_quotes_from_replayed_trades() {
+        let wallet: LocalWallet =
+            "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+                .parse()
+                .unwrap();
+        let input = Input {
+            asset: "ETH".to_string(),
+            target_liquidity: 1.0,
+            half_spread: 5,
+            max_bps_diff: 10,
+            max_absolute_position_size: 10.0,
+            decimals: 1,
+            leverage: 3,
+            is_cross: true,
+            max_margin_utilization: 0.5,
+            min_liquidation_distance_bps: 1000,
+            funding_skew_hours: 0.0,
+            wallet,
+            vault_address: None,
+        };
+        let config = BacktestConfig {
+            simulation: SimulationConfig {
+                sz_decimals: 2,
+                ..SimulationConfig::default()
+            },
+            markout_horizon_ms: 1000,
+            ..BacktestConfig::default()
+        };
+        let mut backtester = Backtester::new(input, config).await.unwrap();
+
+        let report = backtester
+            .run([
+                recorded(
+                    r#"{"time":0,"message":{"channel":"l2Book","data":{"coin":"ETH","time":0,"levels":[[{"px":"100.0","sz":"2","n":1}],[{"px":"100.2","sz":"2","n":1}]]}}}"#,
+                ),
+                recorded(
+                    r#"{"time":0,"message":{"channel":"allMids","data":{"mids":{"ETH":"100.1"}}}}"#,
+                ),
+                recorded(
+                    r#"{"time":500,"message":{"channel":"trades","data":[{"coin":"ETH","side":"A","px":"100.0","sz":"5","time":500,"hash":"0x0"}]}}"#,
+                ),
+                recorded(
+                    r#"{"time":2000,"message":{"channel":"allMids","data":{"mids":{"ETH":"99.9"}}}}"#,
+                ),
+            ])
+            .await;
+
+        assert_eq!(report.fills, 1);
+        assert!((report.final_position - 1.0).abs() < EPSILON);
+        assert!((backtester.market_maker.cur_position - 1.0).abs() < EPSILON);
+        // The bid is quoted at 100.1 and the mid drops to 99.9 afterwards
+        let expected_bps = 0.2 / 100.1 * 10_000.0;
+        assert!((report.adverse_selection_bps - expected_bps).abs() < 1e-6);
+        assert!(report.orders_placed >= 2);
+    }
+}
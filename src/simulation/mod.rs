@@ -0,0 +1,11 @@
+mod backtester;
+mod paper_exchange;
+mod recording;
+mod simulated_exchange;
+
+pub use backtester::{BacktestConfig, BacktestReport, Backtester};
+pub use paper_exchange::PaperExchange;
+pub use recording::{read_recording, RecordedMessage, RecordingWriter};
+pub use simulated_exchange::{
+    SimulatedAccount, SimulatedExchange, SimulatedFill, SimulationConfig,
+};
@@ -0,0 +1,310 @@
+#![warn(clippy::all, clippy::nursery, clippy::pedantic)]
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex, PoisonError,
+};
+
+use ethers::types::H160;
+use tokio::{sync::mpsc::unbounded_channel, task::JoinHandle};
+use tracing::{error, info};
+
+use crate::{
+    helpers::now_timestamp_ms, prelude::*, simulation::SimulatedExchange, BaseUrl,
+    ClientCancelRequest, ClientOrderRequest, Error, ExchangeApi, ExchangeResponseStatus,
+    FundingHistoryResponse, InfoClient, MarketDataApi, Message, OpenOrdersResponse, OrderUpdates,
+    SimulationConfig, SpotUserStateResponse, Subscription, SubscriptionSender, User, UserData,
+    UserStateResponse,
+};
+
+/// Ids handed out for the synthetic account subscriptions, chosen so they
+/// don't collide with the ids of forwarded live subscriptions.
+const FIRST_SYNTHETIC_SUBSCRIPTION_ID: u32 = u32::MAX / 2;
+
+#[derive(Default)]
+struct Subscribers {
+    user_events: Vec<(u32, SubscriptionSender)>,
+    order_updates: Vec<(u32, SubscriptionSender)>,
+}
+
+impl Subscribers {
+    /// Removes the subscriber with the given id, returning whether it
+    /// existed.
+    fn remove(&mut self, subscription_id: u32) -> bool {
+        let count = self.user_events.len() + self.order_updates.len();
+        self.user_events.retain(|&(id, _)| id != subscription_id);
+        self.order_updates.retain(|&(id, _)| id != subscription_id);
+        self.user_events.len() + self.order_updates.len() < count
+    }
+}
+
+/// Aborts the market data feed once the last handle is dropped.
+struct FeedTask(JoinHandle<()>);
+
+impl Drop for FeedTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Paper trading exchange that simulates fills against the live `L2Book` and
+/// `Trades` feeds of a single asset, without sending anything to the
+/// exchange.
+///
+/// Fills and order status changes are published as synthetic `User` and
+/// `OrderUpdates` messages to `UserEvents` and `OrderUpdates` subscribers,
+/// while all other subscriptions are forwarded to the live feed. A strategy
+/// can therefore run unchanged by using the same handle as its exchange and
+/// info client.
+#[derive(Clone)]
+pub struct PaperExchange {
+    exchange: SimulatedExchange,
+    info_client: Arc<tokio::sync::Mutex<InfoClient>>,
+    subscribers: Arc<Mutex<Subscribers>>,
+    next_subscription_id: Arc<AtomicU32>,
+    _feed: Arc<FeedTask>,
+}
+
+impl PaperExchange {
+    /// Connects to the live market data of `asset`. The size decimals of the
+    /// asset are taken from the exchange metadata.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the metadata can't be fetched, the asset is unknown
+    /// or the market data subscriptions fail.
+    pub async fn new(asset: String, config: SimulationConfig, base_url: BaseUrl) -> Result<Self> {
+        let mut info_client = InfoClient::new(None, Some(base_url)).await?;
+        let sz_decimals = info_client
+            .meta()
+            .await?
+            .universe
+            .into_iter()
+            .find(|asset_meta| asset_meta.name == asset)
+            .ok_or(Error::AssetNotFound)?
+            .sz_decimals;
+        let exchange = SimulatedExchange::new(
+            asset.clone(),
+            SimulationConfig {
+                sz_decimals,
+                ..config
+            },
+        );
+
+        let (sender, mut receiver) = unbounded_channel();
+        info_client
+            .subscribe(
+                Subscription::L2Book {
+                    coin: asset.clone(),
+                    n_sig_figs: None,
+                    mantissa: None,
+                },
+                sender.clone(),
+            )
+            .await?;
+        info_client
+            .subscribe(
+                Subscription::Trades {
+                    coin: asset.clone(),
+                },
+                sender,
+            )
+            .await?;
+
+        let subscribers = Arc::new(Mutex::new(Subscribers::default()));
+        let feed = {
+            let exchange = exchange.clone();
+            let subscribers = subscribers.clone();
+            tokio::spawn(async move {
+                while let Some(message) = receiver.recv().await {
+                    exchange.advance_to(now_timestamp_ms());
+                    match message {
+                        Message::L2Book(l2_book) => exchange.on_l2_book(&l2_book.data),
+                        Message::Trades(trades) => exchange.on_trades(&trades.data),
+                        _ => continue,
+                    }
+                    publish(&exchange, &subscribers).await;
+                }
+                error!("Paper trading market data feed for {asset} ended");
+            })
+        };
+        info!("Paper trading {} with simulated fills", exchange.asset());
+
+        Ok(Self {
+            exchange,
+            info_client: Arc::new(tokio::sync::Mutex::new(info_client)),
+            subscribers,
+            next_subscription_id: Arc::new(AtomicU32::new(FIRST_SYNTHETIC_SUBSCRIPTION_ID)),
+            _feed: Arc::new(FeedTask(feed)),
+        })
+    }
+
+    /// The simulated exchange holding the paper account.
+    #[must_use]
+    pub const fn exchange(&self) -> &SimulatedExchange {
+        &self.exchange
+    }
+
+    fn subscribers(&self) -> std::sync::MutexGuard<'_, Subscribers> {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Sends the pending fills and order updates of `exchange` to the
+/// subscribers, dropping subscribers that went away.
+async fn publish(exchange: &SimulatedExchange, subscribers: &Mutex<Subscribers>) {
+    let fills = exchange.take_fills();
+    let order_updates = exchange.take_order_updates();
+    // Bounded subscribers may block, so they're sent to without the lock
+    let (user_events, order_updates_subscribers) = {
+        let subscribers = subscribers.lock().unwrap_or_else(PoisonError::into_inner);
+        (
+            subscribers.user_events.clone(),
+            subscribers.order_updates.clone(),
+        )
+    };
+    let mut gone = Vec::new();
+    if !fills.is_empty() {
+        for (subscription_id, sender) in user_events {
+            let message = Message::User(User {
+                data: UserData {
+                    fills: fills.clone(),
+                },
+            });
+            if sender.send(message).await.is_err() {
+                gone.push(subscription_id);
+            }
+        }
+    }
+    if !order_updates.is_empty() {
+        for (subscription_id, sender) in order_updates_subscribers {
+            let message = Message::OrderUpdates(OrderUpdates {
+                data: order_updates.clone(),
+            });
+            if sender.send(message).await.is_err() {
+                gone.push(subscription_id);
+            }
+        }
+    }
+    if !gone.is_empty() {
+        let mut subscribers = subscribers.lock().unwrap_or_else(PoisonError::into_inner);
+        for subscription_id in gone {
+            subscribers.remove(subscription_id);
+        }
+    }
+}
+
+impl ExchangeApi for PaperExchange {
+    async fn bulk_order(&self, orders: Vec<ClientOrderRequest>) -> Result<ExchangeResponseStatus> {
+        self.exchange.advance_to(now_timestamp_ms());
+        let response = self.exchange.bulk_order(orders).await;
+        publish(&self.exchange, &self.subscribers).await;
+        response
+    }
+
+    async fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        self.exchange.advance_to(now_timestamp_ms());
+        let response = self.exchange.bulk_cancel(cancels).await;
+        publish(&self.exchange, &self.subscribers).await;
+        response
+    }
+
+    async fn update_leverage(
+        &self,
+        leverage: u32,
+        coin: &str,
+        is_cross: bool,
+    ) -> Result<ExchangeResponseStatus> {
+        self.exchange
+            .update_leverage(leverage, coin, is_cross)
+            .await
+    }
+
+    fn sz_decimals(&self, coin: &str) -> Option<u32> {
+        self.exchange.sz_decimals(coin)
+    }
+
+    fn spot_tokens(&self, coin: &str) -> Option<(String, String)> {
+        self.exchange.spot_tokens(coin)
+    }
+}
+
+impl MarketDataApi for PaperExchange {
+    async fn open_orders(&self, address: H160) -> Result<Vec<OpenOrdersResponse>> {
+        self.exchange.advance_to(now_timestamp_ms());
+        MarketDataApi::open_orders(&self.exchange, address).await
+    }
+
+    async fn user_state(&self, address: H160) -> Result<UserStateResponse> {
+        self.exchange.advance_to(now_timestamp_ms());
+        MarketDataApi::user_state(&self.exchange, address).await
+    }
+
+    async fn spot_user_state(&self, address: H160) -> Result<SpotUserStateResponse> {
+        MarketDataApi::spot_user_state(&self.exchange, address).await
+    }
+
+    /// The funding of the live market, although the paper account isn't
+    /// charged it.
+    async fn funding_history(
+        &self,
+        coin: String,
+        start_time: u64,
+        end_time: Option<u64>,
+    ) -> Result<Vec<FundingHistoryResponse>> {
+        self.info_client
+            .lock()
+            .await
+            .funding_history(coin, start_time, end_time)
+            .await
+    }
+
+    /// Account events come from the paper account, everything else is
+    /// forwarded to the live feed.
+    async fn subscribe(
+        &mut self,
+        subscription: Subscription,
+        sender_channel: SubscriptionSender,
+    ) -> Result<u32> {
+        let subscription_id = self.next_subscription_id.fetch_add(1, Ordering::Relaxed);
+        match subscription {
+            Subscription::UserEvents { .. } => self
+                .subscribers()
+                .user_events
+                .push((subscription_id, sender_channel)),
+            Subscription::OrderUpdates { .. } => self
+                .subscribers()
+                .order_updates
+                .push((subscription_id, sender_channel)),
+            subscription => {
+                return self
+                    .info_client
+                    .lock()
+                    .await
+                    .subscribe(subscription, sender_channel)
+                    .await
+            }
+        }
+        Ok(subscription_id)
+    }
+
+    async fn unsubscribe(&mut self, subscription_id: u32) -> Result<()> {
+        if subscription_id < FIRST_SYNTHETIC_SUBSCRIPTION_ID {
+            return self
+                .info_client
+                .lock()
+                .await
+                .unsubscribe(subscription_id)
+                .await;
+        }
+        if !self.subscribers().remove(subscription_id) {
+            return Err(Error::SubscriptionNotFound);
+        }
+        Ok(())
+    }
+}
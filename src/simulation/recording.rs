@@ -0,0 +1,188 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{TimeZone, Utc};
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{prelude::*, Error, Message};
+
+/// How often buffered messages are flushed to disk, in milliseconds, so
+/// that a crash loses at most this much data.
+const FLUSH_INTERVAL_MS: u64 = 1_000;
+
+/// A websocket message together with the local time in milliseconds at
+/// which it was received.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedMessage {
+    pub time: u64,
+    pub message: Message,
+}
+
+/// Reads a recording stored as one JSON encoded `RecordedMessage` per line.
+/// Files ending in `.gz` are decompressed, and a truncated last gzip member
+/// (e.g. from a recorder that crashed) ends the recording early.
+pub fn read_recording(path: &Path) -> Result<Vec<RecordedMessage>> {
+    let file = File::open(path).map_err(|e| Error::Io(e.to_string()))?;
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+
+    let mut messages = Vec::new();
+    for (line_number, line) in BufReader::new(reader).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                warn!("Recording {} is truncated: {e}", path.display());
+                break;
+            }
+            Err(e) => return Err(Error::Io(e.to_string())),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = serde_json::from_str(&line)
+            .map_err(|e| Error::JsonParse(format!("line {}: {e}", line_number + 1)))?;
+        messages.push(message);
+    }
+    Ok(messages)
+}
+
+/// Writes recorded messages as gzip compressed JSON lines, starting a new
+/// file whenever the rotation interval has passed.
+pub struct RecordingWriter {
+    directory: PathBuf,
+    prefix: String,
+    rotation_interval_ms: u64,
+    file_started: u64,
+    last_flush: u64,
+    encoder: Option<GzEncoder<BufWriter<File>>>,
+}
+
+impl RecordingWriter {
+    pub fn new(directory: PathBuf, prefix: String, rotation_interval_ms: u64) -> Result<Self> {
+        fs::create_dir_all(&directory).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(Self {
+            directory,
+            prefix,
+            rotation_interval_ms,
+            file_started: 0,
+            last_flush: 0,
+            encoder: None,
+        })
+    }
+
+    pub fn write(&mut self, message: &RecordedMessage) -> Result<()> {
+        if self.encoder.is_none()
+            || message.time >= self.file_started.saturating_add(self.rotation_interval_ms)
+        {
+            self.rotate(message.time)?;
+        }
+        let encoder = self
+            .encoder
+            .as_mut()
+            .ok_or(Error::Io("recording file not open".to_string()))?;
+
+        let mut line = serde_json::to_vec(message).map_err(|e| Error::JsonParse(e.to_string()))?;
+        line.push(b'\n');
+        encoder
+            .write_all(&line)
+            .map_err(|e| Error::Io(e.to_string()))?;
+
+        if message.time >= self.last_flush + FLUSH_INTERVAL_MS {
+            encoder.flush().map_err(|e| Error::Io(e.to_string()))?;
+            self.last_flush = message.time;
+        }
+        Ok(())
+    }
+
+    /// Completes the current file. Later writes start a new one.
+    pub fn finish(&mut self) -> Result<()> {
+        if let Some(encoder) = self.encoder.take() {
+            encoder
+                .finish()
+                .map_err(|e| Error::Io(e.to_string()))?
+                .flush()
+                .map_err(|e| Error::Io(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self, time: u64) -> Result<()> {
+        self.finish()?;
+
+        let started = Utc
+            .timestamp_millis_opt(time as i64)
+            .single()
+            .ok_or_else(|| Error::GenericParse(format!("invalid timestamp {time}")))?;
+        let path = self.directory.join(format!(
+            "{}-{}.jsonl.gz",
+            self.prefix,
+            started.format("%Y%m%d-%H%M%S%3f")
+        ));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| Error::Io(format!("{}: {e}", path.display())))?;
+
+        self.encoder = Some(GzEncoder::new(BufWriter::new(file), Compression::default()));
+        self.file_started = time;
+        self.last_flush = time;
+        Ok(())
+    }
+}
+
+impl Drop for RecordingWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            warn!("Error finishing recording: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recording_round_trip_with_rotation() -> Result<()> {
+        let directory = std::env::temp_dir().join(format!("recording-{}", uuid::Uuid::new_v4()));
+        let mut writer = RecordingWriter::new(directory.clone(), "test".to_string(), 1_000)?;
+        for time in [0, 500, 1_000, 1_500, 2_500] {
+            let message = serde_json::from_str(&format!(
+                r#"{{"time":{time},"message":{{"channel":"allMids","data":{{"mids":{{"ETH":"100.0"}}}}}}}}"#
+            ))
+            .map_err(|e| Error::JsonParse(e.to_string()))?;
+            writer.write(&message)?;
+        }
+        writer.finish()?;
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(&directory)
+            .map_err(|e| Error::Io(e.to_string()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Io(e.to_string()))?;
+        paths.sort();
+        assert_eq!(paths.len(), 3);
+
+        let mut times = Vec::new();
+        for path in &paths {
+            times.extend(
+                read_recording(path)?
+                    .into_iter()
+                    .map(|message| message.time),
+            );
+        }
+        assert_eq!(times, vec![0, 500, 1_000, 1_500, 2_500]);
+
+        fs::remove_dir_all(directory).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(())
+    }
+}
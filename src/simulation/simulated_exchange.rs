@@ -0,0 +1,998 @@
+#![warn(clippy::all, clippy::nursery, clippy::pedantic)]
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use ethers::types::H160;
+use serde::Deserialize;
+
+use crate::{
+    exchange::{
+        ExchangeDataStatus, ExchangeDataStatuses, ExchangeResponse, FilledOrder, RestingOrder,
+    },
+    helpers::float_to_string_for_hashing,
+    prelude::*,
+    AssetPosition, BasicOrder, ClientCancelRequest, ClientOrder, ClientOrderRequest, Error,
+    ExchangeApi, ExchangeResponseStatus, FundingHistoryResponse, L2BookData, Leverage,
+    MarginSummary, MarketDataApi, OpenOrdersResponse, OrderUpdate, PositionData,
+    SpotUserStateResponse, Subscription, SubscriptionSender, Trade, TradeInfo, UserStateResponse,
+    EPSILON,
+};
+
+/// Parameters of the simulated matching engine.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationConfig {
+    /// Decimals that order sizes must be rounded to.
+    pub sz_decimals: u32,
+    /// Delay in milliseconds until an order or cancel takes effect.
+    pub latency_ms: u64,
+    /// Share of the visible size at our price level that is assumed to be
+    /// queued ahead of a new order. `1.0` puts us at the back of the queue.
+    pub queue_position: f64,
+    pub maker_fee_bps: f64,
+    pub taker_fee_bps: f64,
+    pub initial_balance: f64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            sz_decimals: 0,
+            latency_ms: 0,
+            queue_position: 1.0,
+            maker_fee_bps: 1.5,
+            taker_fee_bps: 4.5,
+            initial_balance: 10_000.0,
+        }
+    }
+}
+
+/// A fill generated by the simulated matching engine.
+#[derive(Debug, Clone)]
+pub struct SimulatedFill {
+    pub time: u64,
+    pub oid: u64,
+    pub is_buy: bool,
+    pub px: f64,
+    pub sz: f64,
+    pub fee: f64,
+    pub crossed: bool,
+}
+
+/// Position and cash flows of the simulated account.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedAccount {
+    pub position: f64,
+    pub entry_px: f64,
+    pub cash: f64,
+    pub fees: f64,
+    pub realized_pnl: f64,
+    pub volume: f64,
+    pub orders_placed: u64,
+    pub orders_filled: u64,
+}
+
+#[derive(Debug)]
+struct SimulatedOrder {
+    oid: u64,
+    cloid: Option<String>,
+    is_buy: bool,
+    px: f64,
+    sz: f64,
+    orig_sz: f64,
+    timestamp: u64,
+    active_at: u64,
+    activated: bool,
+    cancel_at: Option<u64>,
+    queue_ahead: f64,
+    filled: bool,
+}
+
+#[derive(Debug, Default)]
+struct SimulationState {
+    asset: String,
+    config: SimulationConfig,
+    now: u64,
+    next_oid: u64,
+    leverage: u32,
+    is_cross: bool,
+    mark_px: Option<f64>,
+    bids: Vec<(f64, f64)>,
+    asks: Vec<(f64, f64)>,
+    orders: Vec<SimulatedOrder>,
+    account: SimulatedAccount,
+    fill_log: Vec<SimulatedFill>,
+    pending_fills: Vec<TradeInfo>,
+    pending_order_updates: Vec<OrderUpdate>,
+}
+
+/// Matching engine for a single asset that fills resting orders against
+/// replayed or live market data.
+///
+/// Handles are cheap to clone and share the same state, so one handle can
+/// be given to a strategy while another one feeds market data.
+#[derive(Debug, Clone)]
+pub struct SimulatedExchange {
+    asset: String,
+    state: Arc<Mutex<SimulationState>>,
+}
+
+fn parse_px(value: &str) -> f64 {
+    value.parse().unwrap_or_default()
+}
+
+fn ok_response(response_type: &str, statuses: Vec<ExchangeDataStatus>) -> ExchangeResponseStatus {
+    ExchangeResponseStatus::Ok(ExchangeResponse {
+        response_type: response_type.to_string(),
+        data: Some(ExchangeDataStatuses { statuses }),
+    })
+}
+
+impl SimulatedExchange {
+    #[must_use]
+    pub fn new(asset: String, config: SimulationConfig) -> Self {
+        Self {
+            asset: asset.clone(),
+            state: Arc::new(Mutex::new(SimulationState {
+                asset,
+                config,
+                next_oid: 1,
+                leverage: 1,
+                is_cross: true,
+                ..SimulationState::default()
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, SimulationState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[must_use]
+    pub fn asset(&self) -> &str {
+        &self.asset
+    }
+
+    #[must_use]
+    pub fn now(&self) -> u64 {
+        self.state().now
+    }
+
+    /// Moves the simulation clock forward, applying cancels and activating
+    /// orders whose latency has elapsed.
+    pub fn advance_to(&self, time: u64) {
+        let mut state = self.state();
+        state.now = state.now.max(time);
+        state.apply_pending();
+    }
+
+    /// Replaces the simulated book with a new snapshot.
+    pub fn on_l2_book(&self, book: &L2BookData) {
+        if book.coin != self.asset || book.levels.len() < 2 {
+            return;
+        }
+        let mut state = self.state();
+        let parse_levels = |levels: &[crate::BookLevel]| {
+            levels
+                .iter()
+                .map(|level| (parse_px(&level.px), parse_px(&level.sz)))
+                .collect::<Vec<_>>()
+        };
+        state.bids = parse_levels(&book.levels[0]);
+        state.asks = parse_levels(&book.levels[1]);
+        if let (Some(&(bid, _)), Some(&(ask, _))) = (state.bids.first(), state.asks.first()) {
+            state.mark_px = Some(0.5 * (bid + ask));
+        }
+        // Size that left our level can't be ahead of us anymore
+        for i in 0..state.orders.len() {
+            if state.orders[i].activated {
+                let order = &state.orders[i];
+                let level_sz = state.level_size(order.px, order.is_buy);
+                state.orders[i].queue_ahead = state.orders[i].queue_ahead.min(level_sz);
+            }
+        }
+    }
+
+    /// Fills resting orders that the given trades traded through.
+    pub fn on_trades(&self, trades: &[Trade]) {
+        let mut state = self.state();
+        for trade in trades.iter().filter(|trade| trade.coin == self.asset) {
+            state.match_trade(trade);
+        }
+    }
+
+    /// Sets the price positions are marked at.
+    pub fn on_mid(&self, mid: f64) {
+        self.state().mark_px = Some(mid);
+    }
+
+    #[must_use]
+    pub fn mark_px(&self) -> Option<f64> {
+        self.state().mark_px
+    }
+
+    /// Returns the fills generated since the last call, in the format of
+    /// `User` websocket events.
+    #[must_use]
+    pub fn take_fills(&self) -> Vec<TradeInfo> {
+        std::mem::take(&mut self.state().pending_fills)
+    }
+
+    /// Returns the order status changes since the last call, in the format
+    /// of `OrderUpdates` websocket events.
+    #[must_use]
+    pub fn take_order_updates(&self) -> Vec<OrderUpdate> {
+        std::mem::take(&mut self.state().pending_order_updates)
+    }
+
+    #[must_use]
+    pub fn fills(&self) -> Vec<SimulatedFill> {
+        self.state().fill_log.clone()
+    }
+
+    #[must_use]
+    pub fn account(&self) -> SimulatedAccount {
+        self.state().account.clone()
+    }
+
+    /// Total profit including fees, marked at the latest mark price.
+    #[must_use]
+    pub fn pnl(&self) -> f64 {
+        self.state().pnl()
+    }
+}
+
+impl SimulationState {
+    fn pnl(&self) -> f64 {
+        let mark_px = self.mark_px.unwrap_or(self.account.entry_px);
+        self.account.position.mul_add(mark_px, self.account.cash) - self.account.fees
+    }
+
+    fn best_bid(&self) -> Option<f64> {
+        self.bids.first().map(|&(px, _)| px)
+    }
+
+    fn best_ask(&self) -> Option<f64> {
+        self.asks.first().map(|&(px, _)| px)
+    }
+
+    fn level_size(&self, px: f64, is_buy: bool) -> f64 {
+        let levels = if is_buy { &self.bids } else { &self.asks };
+        levels
+            .iter()
+            .find(|&&(level_px, _)| (level_px - px).abs() < EPSILON)
+            .map_or(0.0, |&(_, sz)| sz)
+    }
+
+    fn order_update(&self, order: &SimulatedOrder, status: &str) -> OrderUpdate {
+        OrderUpdate {
+            order: BasicOrder {
+                coin: self.asset.clone(),
+                side: if order.is_buy { "B" } else { "A" }.to_string(),
+                limit_px: float_to_string_for_hashing(order.px),
+                sz: float_to_string_for_hashing(order.sz.max(0.0)),
+                oid: order.oid,
+                timestamp: order.timestamp,
+                orig_sz: float_to_string_for_hashing(order.orig_sz),
+                cloid: order.cloid.clone(),
+            },
+            status: status.to_string(),
+            status_timestamp: self.now,
+        }
+    }
+
+    /// Removes the orders matching `done`, reporting them with `status`.
+    fn remove_orders(&mut self, status: &str, done: impl Fn(&SimulatedOrder) -> bool) {
+        let (removed, kept) = std::mem::take(&mut self.orders)
+            .into_iter()
+            .partition::<Vec<_>, _>(|order| done(order));
+        self.orders = kept;
+        for order in removed {
+            let update = self.order_update(&order, status);
+            self.pending_order_updates.push(update);
+        }
+    }
+
+    fn apply_pending(&mut self) {
+        let now = self.now;
+        self.remove_orders(
+            "canceled",
+            |order| matches!(order.cancel_at, Some(cancel_at) if cancel_at <= now),
+        );
+        for i in 0..self.orders.len() {
+            let order = &self.orders[i];
+            if !order.activated && order.active_at <= now {
+                let queue_ahead =
+                    self.config.queue_position * self.level_size(order.px, order.is_buy);
+                self.orders[i].queue_ahead = queue_ahead;
+                self.orders[i].activated = true;
+            }
+        }
+    }
+
+    fn match_trade(&mut self, trade: &Trade) {
+        let px = parse_px(&trade.px);
+        let mut remaining = parse_px(&trade.sz);
+        // The trade side is the aggressor, so it fills our orders on the other side
+        let aggressor_is_buy = trade.side == "B";
+
+        let mut candidates: Vec<usize> = (0..self.orders.len())
+            .filter(|&i| {
+                let order = &self.orders[i];
+                order.activated
+                    && order.is_buy != aggressor_is_buy
+                    && if order.is_buy {
+                        order.px > px - EPSILON
+                    } else {
+                        order.px < px + EPSILON
+                    }
+            })
+            .collect();
+        // Better priced orders are filled first
+        candidates.sort_by(|&a, &b| {
+            let (a, b) = (&self.orders[a], &self.orders[b]);
+            if a.is_buy {
+                b.px.total_cmp(&a.px)
+            } else {
+                a.px.total_cmp(&b.px)
+            }
+        });
+
+        for i in candidates {
+            if remaining < EPSILON {
+                break;
+            }
+            let order = &mut self.orders[i];
+            if (order.px - px).abs() < EPSILON {
+                let consumed = order.queue_ahead.min(remaining);
+                order.queue_ahead -= consumed;
+                remaining -= consumed;
+            }
+            let fill_sz = order.sz.min(remaining);
+            if fill_sz < EPSILON {
+                continue;
+            }
+            remaining -= fill_sz;
+            let (oid, cloid, is_buy, order_px) =
+                (order.oid, order.cloid.clone(), order.is_buy, order.px);
+            self.fill(oid, cloid, is_buy, order_px, fill_sz, false);
+        }
+        self.remove_orders("filled", |order| order.sz < EPSILON);
+    }
+
+    /// Takes liquidity from the book up to `limit_px`, returning the filled
+    /// size and the average fill price.
+    fn take_liquidity(
+        &mut self,
+        oid: u64,
+        cloid: Option<&String>,
+        is_buy: bool,
+        limit_px: f64,
+        sz: f64,
+    ) -> (f64, f64) {
+        let mut remaining = sz;
+        let mut notional = 0.0;
+        let mut fills = Vec::new();
+        {
+            let levels = if is_buy {
+                &mut self.asks
+            } else {
+                &mut self.bids
+            };
+            for level in levels.iter_mut() {
+                let crosses = if is_buy {
+                    level.0 < limit_px + EPSILON
+                } else {
+                    level.0 > limit_px - EPSILON
+                };
+                if remaining < EPSILON || !crosses {
+                    break;
+                }
+                let fill_sz = level.1.min(remaining);
+                level.1 -= fill_sz;
+                remaining -= fill_sz;
+                notional += fill_sz * level.0;
+                fills.push((level.0, fill_sz));
+            }
+            levels.retain(|level| level.1 > EPSILON);
+        }
+        for (px, fill_sz) in fills {
+            self.fill(oid, cloid.cloned(), is_buy, px, fill_sz, true);
+        }
+        let filled = sz - remaining;
+        let avg_px = if filled > EPSILON {
+            notional / filled
+        } else {
+            0.0
+        };
+        (filled, avg_px)
+    }
+
+    fn fill(
+        &mut self,
+        oid: u64,
+        cloid: Option<String>,
+        is_buy: bool,
+        px: f64,
+        sz: f64,
+        crossed: bool,
+    ) {
+        let fee_bps = if crossed {
+            self.config.taker_fee_bps
+        } else {
+            self.config.maker_fee_bps
+        };
+        let fee = px * sz * fee_bps / 10_000.0;
+        let signed_sz = if is_buy { sz } else { -sz };
+
+        let account = &mut self.account;
+        let start_position = account.position;
+        let new_position = start_position + signed_sz;
+        let closed_pnl = if start_position * signed_sz < 0.0 {
+            sz.min(start_position.abs()) * (px - account.entry_px) * start_position.signum()
+        } else {
+            0.0
+        };
+        if new_position.abs() < EPSILON {
+            account.entry_px = 0.0;
+        } else if start_position * signed_sz >= 0.0 {
+            account.entry_px =
+                account.entry_px.mul_add(start_position.abs(), px * sz) / new_position.abs();
+        } else if new_position * start_position < 0.0 {
+            account.entry_px = px;
+        }
+        account.position = new_position;
+        account.cash -= signed_sz * px;
+        account.fees += fee;
+        account.realized_pnl += closed_pnl;
+        account.volume += px * sz;
+
+        let dir = match (is_buy, start_position > EPSILON, start_position < -EPSILON) {
+            (true, _, true) if new_position > EPSILON => "Short > Long",
+            (true, _, true) => "Close Short",
+            (true, _, _) => "Open Long",
+            (false, true, _) if new_position < -EPSILON => "Long > Short",
+            (false, true, _) => "Close Long",
+            (false, _, _) => "Open Short",
+        };
+
+        // Orders taking liquidity aren't on the book yet and are counted when placed
+        if let Some(order) = self.orders.iter_mut().find(|order| order.oid == oid) {
+            order.sz -= sz;
+            if !order.filled {
+                order.filled = true;
+                self.account.orders_filled += 1;
+            }
+        }
+
+        self.fill_log.push(SimulatedFill {
+            time: self.now,
+            oid,
+            is_buy,
+            px,
+            sz,
+            fee,
+            crossed,
+        });
+        self.pending_fills.push(TradeInfo {
+            coin: self.asset.clone(),
+            side: if is_buy { "B" } else { "A" }.to_string(),
+            px: float_to_string_for_hashing(px),
+            sz: float_to_string_for_hashing(sz),
+            time: self.now,
+            hash: format!("{:#066x}", self.fill_log.len()),
+            start_position: float_to_string_for_hashing(start_position),
+            dir: dir.to_string(),
+            closed_pnl: float_to_string_for_hashing(closed_pnl),
+            oid,
+            cloid,
+            crossed,
+            fee: float_to_string_for_hashing(fee),
+        });
+    }
+
+    fn place(&mut self, order: ClientOrderRequest) -> ExchangeDataStatus {
+        let tif = match order.order_type {
+            ClientOrder::Limit(limit) => limit.tif,
+            ClientOrder::Trigger(_) => {
+                return ExchangeDataStatus::Error(
+                    "Trigger orders are not supported in simulation.".to_string(),
+                )
+            }
+        };
+        let scaled_sz = order.sz * 10f64.powi(self.config.sz_decimals.try_into().unwrap_or(0));
+        if order.sz < EPSILON || (scaled_sz - scaled_sz.round()).abs() > 1e-6 {
+            return ExchangeDataStatus::Error("Order has invalid size.".to_string());
+        }
+
+        let crosses = if order.is_buy {
+            self.best_ask()
+                .is_some_and(|ask| order.limit_px > ask - EPSILON)
+        } else {
+            self.best_bid()
+                .is_some_and(|bid| order.limit_px < bid + EPSILON)
+        };
+        match tif.as_str() {
+            "Alo" if crosses => {
+                return ExchangeDataStatus::Error(format!(
+                    "Post only order would have immediately matched, bbo was {}@{}. asset={}",
+                    self.best_bid().unwrap_or_default(),
+                    self.best_ask().unwrap_or_default(),
+                    self.asset,
+                ))
+            }
+            "Alo" | "Gtc" | "Ioc" => {}
+            _ => return ExchangeDataStatus::Error("Invalid TIF.".to_string()),
+        }
+
+        let oid = self.next_oid;
+        self.next_oid += 1;
+        self.account.orders_placed += 1;
+        let cloid = order.cloid.map(crate::helpers::uuid_to_hex_string);
+        let (filled, avg_px) = if crosses {
+            self.take_liquidity(oid, cloid.as_ref(), order.is_buy, order.limit_px, order.sz)
+        } else {
+            (0.0, 0.0)
+        };
+        if filled > EPSILON {
+            self.account.orders_filled += 1;
+        }
+
+        let resting = SimulatedOrder {
+            oid,
+            cloid,
+            is_buy: order.is_buy,
+            px: order.limit_px,
+            sz: order.sz - filled,
+            orig_sz: order.sz,
+            timestamp: self.now,
+            active_at: self.now + self.config.latency_ms,
+            activated: false,
+            cancel_at: None,
+            queue_ahead: 0.0,
+            filled: filled > EPSILON,
+        };
+        let filled_status = || {
+            ExchangeDataStatus::Filled(FilledOrder {
+                total_sz: float_to_string_for_hashing(filled),
+                avg_px: float_to_string_for_hashing(avg_px),
+                oid,
+            })
+        };
+        if resting.sz < EPSILON {
+            let update = self.order_update(&resting, "filled");
+            self.pending_order_updates.push(update);
+            return filled_status();
+        }
+        if tif == "Ioc" {
+            if filled < EPSILON {
+                return ExchangeDataStatus::Error(format!(
+                    "Order could not immediately match against any resting orders. asset={}",
+                    self.asset
+                ));
+            }
+            // The unfilled rest of an immediate or cancel order is dropped
+            let update = self.order_update(&resting, "canceled");
+            self.pending_order_updates.push(update);
+            return filled_status();
+        }
+
+        let update = self.order_update(&resting, "open");
+        self.pending_order_updates.push(update);
+        self.orders.push(resting);
+        self.apply_pending();
+        ExchangeDataStatus::Resting(RestingOrder { oid })
+    }
+
+    fn cancel(&mut self, oid: u64) -> ExchangeDataStatus {
+        let cancel_at = self.now + self.config.latency_ms;
+        match self
+            .orders
+            .iter_mut()
+            .find(|order| order.oid == oid && order.cancel_at.is_none())
+        {
+            Some(order) => {
+                order.cancel_at = Some(cancel_at);
+                self.apply_pending();
+                ExchangeDataStatus::Success
+            }
+            None => ExchangeDataStatus::Error(
+                "Order was never placed, already canceled, or filled.".to_string(),
+            ),
+        }
+    }
+
+    fn user_state(&self) -> UserStateResponse {
+        let mark_px = self.mark_px.unwrap_or(self.account.entry_px);
+        let account_value = self.config.initial_balance + self.pnl();
+        let position_value = self.account.position.abs() * mark_px;
+        let margin_used = position_value / f64::from(self.leverage.max(1));
+        let unrealized_pnl = self.account.position * (mark_px - self.account.entry_px);
+
+        let asset_positions = if self.account.position.abs() > EPSILON {
+            vec![AssetPosition {
+                position: PositionData {
+                    coin: self.asset.clone(),
+                    entry_px: Some(float_to_string_for_hashing(self.account.entry_px)),
+                    leverage: Leverage {
+                        type_string: if self.is_cross { "cross" } else { "isolated" }.to_string(),
+                        value: self.leverage,
+                        raw_usd: None,
+                    },
+                    liquidation_px: None,
+                    margin_used: float_to_string_for_hashing(margin_used),
+                    position_value: float_to_string_for_hashing(position_value),
+                    return_on_equity: float_to_string_for_hashing(if margin_used > EPSILON {
+                        unrealized_pnl / margin_used
+                    } else {
+                        0.0
+                    }),
+                    szi: float_to_string_for_hashing(self.account.position),
+                    unrealized_pnl: float_to_string_for_hashing(unrealized_pnl),
+                },
+                type_string: "oneWay".to_string(),
+            }]
+        } else {
+            Vec::new()
+        };
+        let margin_summary = || MarginSummary {
+            account_value: float_to_string_for_hashing(account_value),
+            total_margin_used: float_to_string_for_hashing(margin_used),
+            total_ntl_pos: float_to_string_for_hashing(position_value),
+            total_raw_usd: float_to_string_for_hashing(
+                self.config.initial_balance + self.account.cash - self.account.fees,
+            ),
+        };
+
+        UserStateResponse {
+            asset_positions,
+            cross_margin_summary: margin_summary(),
+            margin_summary: margin_summary(),
+            withdrawable: float_to_string_for_hashing((account_value - margin_used).max(0.0)),
+        }
+    }
+
+    fn open_orders(&self) -> Vec<OpenOrdersResponse> {
+        self.orders
+            .iter()
+            .filter(|order| order.cancel_at.is_none())
+            .map(|order| OpenOrdersResponse {
+                coin: self.asset.clone(),
+                limit_px: float_to_string_for_hashing(order.px),
+                oid: order.oid,
+                side: if order.is_buy { "B" } else { "A" }.to_string(),
+                sz: float_to_string_for_hashing(order.sz),
+                timestamp: order.timestamp,
+            })
+            .collect()
+    }
+}
+
+impl ExchangeApi for SimulatedExchange {
+    async fn bulk_order(&self, orders: Vec<ClientOrderRequest>) -> Result<ExchangeResponseStatus> {
+        if orders.iter().any(|order| order.asset != self.asset) {
+            return Err(Error::AssetNotFound);
+        }
+        let mut state = self.state();
+        let statuses = orders.into_iter().map(|order| state.place(order)).collect();
+        drop(state);
+        Ok(ok_response("order", statuses))
+    }
+
+    async fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        if cancels.iter().any(|cancel| cancel.asset != self.asset) {
+            return Err(Error::AssetNotFound);
+        }
+        let mut state = self.state();
+        let statuses = cancels
+            .into_iter()
+            .map(|cancel| state.cancel(cancel.oid))
+            .collect();
+        drop(state);
+        Ok(ok_response("cancel", statuses))
+    }
+
+    async fn update_leverage(
+        &self,
+        leverage: u32,
+        coin: &str,
+        is_cross: bool,
+    ) -> Result<ExchangeResponseStatus> {
+        if coin != self.asset {
+            return Err(Error::AssetNotFound);
+        }
+        {
+            let mut state = self.state();
+            state.leverage = leverage;
+            state.is_cross = is_cross;
+        }
+        Ok(ExchangeResponseStatus::Ok(ExchangeResponse {
+            response_type: "default".to_string(),
+            data: None,
+        }))
+    }
+
+    fn sz_decimals(&self, coin: &str) -> Option<u32> {
+        (coin == self.asset).then(|| self.state().config.sz_decimals)
+    }
+
+    /// The simulated asset is always a perp.
+    fn spot_tokens(&self, _coin: &str) -> Option<(String, String)> {
+        None
+    }
+}
+
+impl MarketDataApi for SimulatedExchange {
+    async fn open_orders(&self, _address: H160) -> Result<Vec<OpenOrdersResponse>> {
+        Ok(self.state().open_orders())
+    }
+
+    async fn user_state(&self, _address: H160) -> Result<UserStateResponse> {
+        Ok(self.state().user_state())
+    }
+
+    async fn spot_user_state(&self, _address: H160) -> Result<SpotUserStateResponse> {
+        Ok(SpotUserStateResponse {
+            balances: Vec::new(),
+        })
+    }
+
+    /// No funding is charged by the simulated exchange.
+    async fn funding_history(
+        &self,
+        _coin: String,
+        _start_time: u64,
+        _end_time: Option<u64>,
+    ) -> Result<Vec<FundingHistoryResponse>> {
+        Ok(Vec::new())
+    }
+
+    /// Market data is fed in by the caller, so there is nothing to
+    /// subscribe to.
+    async fn subscribe(
+        &mut self,
+        _subscription: Subscription,
+        _sender_channel: SubscriptionSender,
+    ) -> Result<u32> {
+        Err(Error::Websocket(
+            "Subscriptions aren't supported by the simulated exchange".to_string(),
+        ))
+    }
+
+    async fn unsubscribe(&mut self, _subscription_id: u32) -> Result<()> {
+        Err(Error::SubscriptionNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BookLevel, ClientLimit};
+
+    fn book(bid: (&str, &str), ask: (&str, &str)) -> L2BookData {
+        let level = |(px, sz): (&str, &str)| BookLevel {
+            px: px.to_string(),
+            sz: sz.to_string(),
+            n: 1,
+        };
+        L2BookData {
+            coin: "ETH".to_string(),
+            time: 0,
+            levels: vec![vec![level(bid)], vec![level(ask)]],
+        }
+    }
+
+    fn trade(side: &str, px: &str, sz: &str) -> Trade {
+        Trade {
+            coin: "ETH".to_string(),
+            side: side.to_string(),
+            px: px.to_string(),
+            sz: sz.to_string(),
+            time: 0,
+            hash: String::new(),
+        }
+    }
+
+    fn limit(is_buy: bool, limit_px: f64, sz: f64, tif: &str) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy,
+            reduce_only: false,
+            limit_px,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit {
+                tif: tif.to_string(),
+            }),
+        }
+    }
+
+    fn first_status(response: ExchangeResponseStatus) -> ExchangeDataStatus {
+        match response {
+            ExchangeResponseStatus::Ok(response) => response.data.unwrap().statuses[0].clone(),
+            ExchangeResponseStatus::Err(e) => panic!("unexpected error response: {e}"),
+        }
+    }
+
+    fn exchange() -> SimulatedExchange {
+        SimulatedExchange::new(
+            "ETH".to_string(),
+            SimulationConfig {
+                sz_decimals: 2,
+                maker_fee_bps: 0.0,
+                taker_fee_bps: 0.0,
+                ..SimulationConfig::default()
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_resting_order_waits_for_queue_ahead() {
+        let exchange = exchange();
+        exchange.on_l2_book(&book(("100", "2"), ("101", "2")));
+
+        let status = first_status(
+            exchange
+                .order(limit(true, 100.0, 1.0, "Alo"))
+                .await
+                .unwrap(),
+        );
+        assert!(matches!(status, ExchangeDataStatus::Resting(_)));
+
+        // The two lots queued ahead of us trade first
+        exchange.on_trades(&[trade("A", "100", "1.5")]);
+        assert!(exchange.take_fills().is_empty());
+
+        exchange.on_trades(&[trade("A", "100", "1")]);
+        let fills = exchange.take_fills();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].sz, "0.5");
+        assert_eq!(fills[0].dir, "Open Long");
+        assert!((exchange.account().position - 0.5).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_order_updates_follow_order_lifecycle() {
+        let exchange = exchange();
+        exchange.on_l2_book(&book(("100", "2"), ("101", "2")));
+
+        let ExchangeDataStatus::Resting(RestingOrder { oid }) =
+            first_status(exchange.order(limit(true, 99.0, 1.0, "Alo")).await.unwrap())
+        else {
+            panic!("order should rest");
+        };
+        exchange
+            .order(limit(false, 102.0, 1.0, "Alo"))
+            .await
+            .unwrap();
+        exchange.on_trades(&[trade("B", "102", "1")]);
+        exchange
+            .cancel(ClientCancelRequest {
+                asset: "ETH".to_string(),
+                oid,
+            })
+            .await
+            .unwrap();
+
+        let updates: Vec<(u64, String)> = exchange
+            .take_order_updates()
+            .into_iter()
+            .map(|update| (update.order.oid, update.status))
+            .collect();
+        assert_eq!(
+            updates,
+            vec![
+                (oid, "open".to_string()),
+                (oid + 1, "open".to_string()),
+                (oid + 1, "filled".to_string()),
+                (oid, "canceled".to_string()),
+            ]
+        );
+        assert!(exchange.take_order_updates().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_post_only_order_crossing_the_book_is_rejected() {
+        let exchange = exchange();
+        exchange.on_l2_book(&book(("100", "2"), ("101", "2")));
+
+        let status = first_status(
+            exchange
+                .order(limit(true, 101.0, 1.0, "Alo"))
+                .await
+                .unwrap(),
+        );
+        assert!(
+            matches!(status, ExchangeDataStatus::Error(e) if e.starts_with("Post only order would have immediately matched"))
+        );
+        assert_eq!(exchange.account().orders_placed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_takes_effect_after_latency() {
+        let exchange = SimulatedExchange::new(
+            "ETH".to_string(),
+            SimulationConfig {
+                sz_decimals: 2,
+                latency_ms: 100,
+                queue_position: 0.0,
+                ..SimulationConfig::default()
+            },
+        );
+        exchange.on_l2_book(&book(("100", "2"), ("101", "2")));
+        let ExchangeDataStatus::Resting(RestingOrder { oid }) = first_status(
+            exchange
+                .order(limit(false, 101.0, 1.0, "Alo"))
+                .await
+                .unwrap(),
+        ) else {
+            panic!("order should rest");
+        };
+
+        // Not active yet, so trades don't fill it
+        exchange.advance_to(50);
+        exchange.on_trades(&[trade("B", "101", "1")]);
+        assert!(exchange.take_fills().is_empty());
+
+        exchange.advance_to(100);
+        let status = first_status(
+            exchange
+                .cancel(ClientCancelRequest {
+                    asset: "ETH".to_string(),
+                    oid,
+                })
+                .await
+                .unwrap(),
+        );
+        assert!(matches!(status, ExchangeDataStatus::Success));
+
+        // The cancel is still in flight, so the order can fill
+        exchange.advance_to(150);
+        exchange.on_trades(&[trade("B", "101", "0.4")]);
+        assert_eq!(exchange.take_fills().len(), 1);
+
+        exchange.advance_to(200);
+        exchange.on_trades(&[trade("B", "101", "1")]);
+        assert!(exchange.take_fills().is_empty());
+        assert!(exchange.open_orders(H160::zero()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_taker_fills_track_realized_pnl_and_fees() {
+        let exchange = SimulatedExchange::new(
+            "ETH".to_string(),
+            SimulationConfig {
+                sz_decimals: 2,
+                taker_fee_bps: 10.0,
+                ..SimulationConfig::default()
+            },
+        );
+        exchange.on_l2_book(&book(("100", "5"), ("101", "5")));
+        let status = first_status(
+            exchange
+                .order(limit(true, 101.0, 2.0, "Ioc"))
+                .await
+                .unwrap(),
+        );
+        assert!(matches!(status, ExchangeDataStatus::Filled(_)));
+
+        exchange.on_l2_book(&book(("111", "5"), ("112", "5")));
+        let status = first_status(
+            exchange
+                .order(limit(false, 111.0, 3.0, "Ioc"))
+                .await
+                .unwrap(),
+        );
+        assert!(matches!(status, ExchangeDataStatus::Filled(_)));
+
+        let account = exchange.account();
+        assert!((account.position + 1.0).abs() < EPSILON);
+        assert!((account.entry_px - 111.0).abs() < EPSILON);
+        assert!((account.realized_pnl - 20.0).abs() < EPSILON);
+        assert!((account.fees - 0.535).abs() < EPSILON);
+        assert_eq!(exchange.take_fills()[1].dir, "Long > Short");
+    }
+}
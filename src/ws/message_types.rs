@@ -1,37 +1,89 @@
 use crate::ws::sub_structs::*;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Trades {
     pub data: Vec<Trade>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct L2Book {
     pub data: L2BookData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AllMids {
     pub data: AllMidsData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct User {
     pub data: UserData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct UserFills {
     pub data: UserFillsData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Candle {
     pub data: CandleData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ActiveAssetCtx {
+    pub data: ActiveAssetCtxData,
+}
+
+/// Update of an `ActiveAssetCtx` subscription to a spot pair.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ActiveSpotAssetCtx {
+    pub data: ActiveSpotAssetCtxData,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct OrderUpdates {
     pub data: Vec<OrderUpdate>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WebData2 {
+    pub data: WebData2Data,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Notification {
+    pub data: NotificationData,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UserFundings {
+    pub data: UserFundingsData,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UserNonFundingLedgerUpdates {
+    pub data: UserNonFundingLedgerUpdatesData,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ActiveAssetData {
+    pub data: ActiveAssetDataData,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Bbo {
+    pub data: BboData,
+}
+
+/// Error the server sent, e.g. for an invalid subscription.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ErrorMessage {
+    pub data: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Post {
+    pub data: PostData,
+}
@@ -1,7 +1,11 @@
 mod message_types;
 mod sub_structs;
+mod subscription_channel;
 mod ws_manager;
 pub use message_types::*;
 pub use sub_structs::*;
-pub(crate) use ws_manager::WsManager;
+pub use subscription_channel::{
+    bounded_channel, BoundedReceiver, BoundedSender, OverflowPolicy, SubscriptionSender,
+};
+pub(crate) use ws_manager::{ConnectionKey, PostRequest, WsManager};
 pub use ws_manager::{Message, Subscription};
@@ -1,8 +1,10 @@
 use ethers::types::H160;
 use gxhash::HashMap;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Clone, Debug)]
+use crate::{AssetCtx, FundingDelta, LedgerUpdate, Leverage, SpotAssetCtx};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Trade {
     pub coin: String,
     pub side: String,
@@ -12,26 +14,38 @@ pub struct Trade {
     pub hash: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct BookLevel {
     pub px: String,
     pub sz: String,
     pub n: u64,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct L2BookData {
     pub coin: String,
     pub time: u64,
     pub levels: Vec<Vec<BookLevel>>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ActiveAssetCtxData {
+    pub coin: String,
+    pub ctx: AssetCtx,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ActiveSpotAssetCtxData {
+    pub coin: String,
+    pub ctx: SpotAssetCtx,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AllMidsData {
     pub mids: HashMap<String, String>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct TradeInfo {
     pub coin: String,
@@ -51,18 +65,18 @@ pub struct TradeInfo {
     pub fee: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct UserFillsData {
     pub user: H160,
     pub fills: Vec<TradeInfo>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct UserData {
     pub fills: Vec<TradeInfo>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CandleData {
     #[serde(rename = "T")]
     pub time_close: u64,
@@ -86,7 +100,7 @@ pub struct CandleData {
     pub volume: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct OrderUpdate {
     pub order: BasicOrder,
@@ -94,7 +108,7 @@ pub struct OrderUpdate {
     pub status_timestamp: u64,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct BasicOrder {
     pub coin: String,
@@ -106,3 +120,88 @@ pub struct BasicOrder {
     pub orig_sz: String,
     pub cloid: Option<String>,
 }
+
+/// The account summary shown in the frontend. Its fields change often, so
+/// they are kept as JSON.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WebData2Data {
+    pub user: H160,
+    #[serde(flatten)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotificationData {
+    pub notification: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UserFunding {
+    pub time: u64,
+    #[serde(flatten)]
+    pub delta: FundingDelta,
+}
+
+/// Funding payments of `user`. The first message after subscribing is a
+/// snapshot of the recent ones.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserFundingsData {
+    pub user: H160,
+    #[serde(default)]
+    pub is_snapshot: bool,
+    pub fundings: Vec<UserFunding>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NonFundingLedgerUpdate {
+    pub time: u64,
+    pub hash: String,
+    pub delta: LedgerUpdate,
+}
+
+/// Deposits, withdrawals and transfers of `user`. The first message after
+/// subscribing is a snapshot of the recent ones.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserNonFundingLedgerUpdatesData {
+    pub user: H160,
+    #[serde(default)]
+    pub is_snapshot: bool,
+    pub non_funding_ledger_updates: Vec<NonFundingLedgerUpdate>,
+}
+
+/// Leverage of `user` for `coin` and the sizes it can trade, as bid and ask.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveAssetDataData {
+    pub user: H160,
+    pub coin: String,
+    pub leverage: Leverage,
+    pub max_trade_szs: [String; 2],
+    pub available_to_trade: [String; 2],
+}
+
+/// Best bid and offer of `coin`, a side is `None` while it's empty.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BboData {
+    pub coin: String,
+    pub time: u64,
+    pub bbo: [Option<BookLevel>; 2],
+}
+
+/// Response to a request sent with the websocket `post` method. Actions are
+/// answered with the body an HTTP request would have returned.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", content = "payload", rename_all = "camelCase")]
+pub enum PostResponse {
+    Info(serde_json::Value),
+    Action(serde_json::Value),
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PostData {
+    pub id: u64,
+    pub response: PostResponse,
+}
@@ -0,0 +1,298 @@
+use std::{
+    collections::VecDeque,
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+use tokio::sync::{mpsc::UnboundedSender, Notify};
+
+use crate::{metrics::record_ws_dropped, prelude::*, ws::WsManager, Error, Message};
+
+/// What a bounded subscription channel does with a message that arrives
+/// while it's full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued message to make room.
+    #[default]
+    DropOldest,
+    /// Replace the queued message of the same subscription by the new one
+    /// if it's a snapshot, like mids, books and asset contexts, whose
+    /// previous state doesn't matter anymore. This happens even if the
+    /// channel isn't full, so the consumer only ever sees the latest
+    /// snapshot. Other messages are queued, dropping the oldest message if
+    /// the channel is full.
+    KeepLatest,
+    /// Wait until the consumer made room. This stalls the websocket
+    /// connection, and so all subscriptions on it, until then.
+    Block,
+}
+
+#[derive(Debug)]
+struct Shared {
+    /// Queued messages with the conflation key of snapshots.
+    queue: Mutex<VecDeque<(Option<String>, Message)>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    senders: AtomicUsize,
+    receiver_closed: AtomicBool,
+    message_sent: Notify,
+    message_received: Notify,
+    dropped: AtomicU64,
+}
+
+impl Shared {
+    fn queue(&self) -> std::sync::MutexGuard<'_, VecDeque<(Option<String>, Message)>> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn record_dropped(&self, message: &Message) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        record_ws_dropped(WsManager::channel(message));
+    }
+
+    /// Queues `message` unless the channel is full and blocks, in which case
+    /// it's handed back.
+    fn push(&self, key: Option<String>, message: Message) -> Option<Message> {
+        let mut queue = self.queue();
+        if key.is_some() {
+            if let Some(queued) = queue.iter_mut().find(|(queued_key, _)| *queued_key == key) {
+                let replaced = std::mem::replace(&mut queued.1, message);
+                drop(queue);
+                self.record_dropped(&replaced);
+                return None;
+            }
+        }
+        if queue.len() >= self.capacity {
+            if self.policy == OverflowPolicy::Block {
+                return Some(message);
+            }
+            if let Some((_, oldest)) = queue.pop_front() {
+                self.record_dropped(&oldest);
+            }
+        }
+        queue.push_back((key, message));
+        drop(queue);
+        self.message_sent.notify_one();
+        None
+    }
+}
+
+/// Creates a channel for subscription messages that holds at most
+/// `capacity` messages, handling any more according to `policy`.
+#[must_use]
+pub fn bounded_channel(
+    capacity: usize,
+    policy: OverflowPolicy,
+) -> (BoundedSender, BoundedReceiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::with_capacity(capacity)),
+        capacity: capacity.max(1),
+        policy,
+        senders: AtomicUsize::new(1),
+        receiver_closed: AtomicBool::new(false),
+        message_sent: Notify::new(),
+        message_received: Notify::new(),
+        dropped: AtomicU64::new(0),
+    });
+    (
+        BoundedSender {
+            shared: shared.clone(),
+        },
+        BoundedReceiver { shared },
+    )
+}
+
+#[derive(Debug)]
+pub struct BoundedSender {
+    shared: Arc<Shared>,
+}
+
+impl BoundedSender {
+    /// Sends `message`, waiting for room if the channel blocks when full.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the receiver was dropped.
+    pub async fn send(&self, message: Message) -> Result<()> {
+        let key = if self.shared.policy == OverflowPolicy::KeepLatest {
+            WsManager::conflation_key(&message)
+        } else {
+            None
+        };
+        let mut message = message;
+        loop {
+            // Registered before checking for room, so that a message
+            // received in between isn't missed
+            let mut message_received = pin!(self.shared.message_received.notified());
+            message_received.as_mut().enable();
+            if self.shared.receiver_closed.load(Ordering::Acquire) {
+                return Err(Error::WsSend("Subscription receiver dropped".to_string()));
+            }
+            match self.shared.push(key.clone(), message) {
+                None => return Ok(()),
+                Some(returned) => message = returned,
+            }
+            message_received.await;
+        }
+    }
+
+    /// Number of messages dropped or replaced because the channel was full.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Clone for BoundedSender {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::AcqRel);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for BoundedSender {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.message_sent.notify_one();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BoundedReceiver {
+    shared: Arc<Shared>,
+}
+
+impl BoundedReceiver {
+    /// Receives the next message, or `None` once all senders were dropped
+    /// and no messages are left.
+    pub async fn recv(&mut self) -> Option<Message> {
+        loop {
+            if let Some((_, message)) = self.shared.queue().pop_front() {
+                self.shared.message_received.notify_waiters();
+                return Some(message);
+            }
+            if self.shared.senders.load(Ordering::Acquire) == 0 {
+                return self.shared.queue().pop_front().map(|(_, message)| message);
+            }
+            self.shared.message_sent.notified().await;
+        }
+    }
+
+    /// Number of messages dropped or replaced because the channel was full.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for BoundedReceiver {
+    fn drop(&mut self) {
+        self.shared.receiver_closed.store(true, Ordering::Release);
+        self.shared.message_received.notify_waiters();
+    }
+}
+
+/// Where the messages of a subscription are sent to.
+#[derive(Clone, Debug)]
+pub enum SubscriptionSender {
+    Unbounded(UnboundedSender<Message>),
+    Bounded(BoundedSender),
+}
+
+impl SubscriptionSender {
+    /// Sends `message`, waiting for room if it's a bounded channel that
+    /// blocks when full.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the receiver was dropped.
+    pub async fn send(&self, message: Message) -> Result<()> {
+        match self {
+            Self::Unbounded(sender) => sender
+                .send(message)
+                .map_err(|e| Error::WsSend(e.to_string())),
+            Self::Bounded(sender) => sender.send(message).await,
+        }
+    }
+}
+
+impl From<UnboundedSender<Message>> for SubscriptionSender {
+    fn from(sender: UnboundedSender<Message>) -> Self {
+        Self::Unbounded(sender)
+    }
+}
+
+impl From<BoundedSender> for SubscriptionSender {
+    fn from(sender: BoundedSender) -> Self {
+        Self::Bounded(sender)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::*;
+    use crate::{AllMids, AllMidsData, User, UserData};
+
+    fn mids(mid: &str) -> Message {
+        Message::AllMids(AllMids {
+            data: AllMidsData {
+                mids: [("ETH".to_string(), mid.to_string())].into_iter().collect(),
+            },
+        })
+    }
+
+    fn user() -> Message {
+        Message::User(User {
+            data: UserData { fills: Vec::new() },
+        })
+    }
+
+    fn mid(message: Option<Message>) -> String {
+        match message {
+            Some(Message::AllMids(all_mids)) => all_mids.data.mids["ETH"].clone(),
+            message => panic!("unexpected message {message:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overflow_policies() -> Result<()> {
+        let (sender, mut receiver) = bounded_channel(2, OverflowPolicy::DropOldest);
+        for mid in ["1", "2", "3"] {
+            sender.send(mids(mid)).await?;
+        }
+        assert_eq!(mid(receiver.recv().await), "2");
+        assert_eq!(receiver.dropped(), 1);
+
+        // Mids are conflated while user events are kept in order
+        let (sender, mut receiver) = bounded_channel(2, OverflowPolicy::KeepLatest);
+        sender.send(mids("1")).await?;
+        sender.send(user()).await?;
+        sender.send(mids("2")).await?;
+        assert_eq!(mid(receiver.recv().await), "2");
+        assert!(matches!(receiver.recv().await, Some(Message::User(_))));
+        assert_eq!(sender.dropped(), 1);
+
+        let (sender, mut receiver) = bounded_channel(1, OverflowPolicy::Block);
+        sender.send(mids("1")).await?;
+        assert!(timeout(Duration::from_millis(20), sender.send(mids("2")))
+            .await
+            .is_err());
+        let blocked = tokio::spawn(async move { sender.send(mids("3")).await });
+        assert_eq!(mid(receiver.recv().await), "1");
+        blocked.await.unwrap()?;
+        assert_eq!(mid(receiver.recv().await), "3");
+        assert_eq!(receiver.dropped(), 0);
+
+        // The spawned task dropped the last sender
+        assert!(receiver.recv().await.is_none());
+        Ok(())
+    }
+}
@@ -1,52 +1,197 @@
 use crate::{
+    metrics::{
+        record_ws_connect, record_ws_disconnect, record_ws_dispatch, record_ws_message,
+        record_ws_reconnect,
+    },
     prelude::*,
-    ws::message_types::{AllMids, Candle, L2Book, OrderUpdates, Trades, User},
-    Error, UserFills,
+    ws::message_types::{
+        ActiveAssetCtx, ActiveAssetData, ActiveSpotAssetCtx, AllMids, Bbo, Candle, ErrorMessage,
+        L2Book, Notification, OrderUpdates, Post, Trades, User, UserFundings,
+        UserNonFundingLedgerUpdates, WebData2,
+    },
+    Error, PostResponse, SubscriptionSender, UserFills,
+};
+use futures_util::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
 };
-use futures_util::{stream::SplitSink, SinkExt, StreamExt};
 use gxhash::{HashMap, HashMapExt};
-use log::error;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use serde_json::Value;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use tokio::{
     net::TcpStream,
     spawn,
-    sync::{mpsc::UnboundedSender, Mutex},
-};
-use tokio_tungstenite::{
-    connect_async,
-    tungstenite::{self, protocol},
-    MaybeTlsStream, WebSocketStream,
+    sync::{oneshot, Mutex},
+    task::JoinHandle,
+    time::{sleep, timeout},
 };
+use tokio_tungstenite::{connect_async, tungstenite::protocol, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
 use ethers::types::H160;
 
 #[derive(Debug)]
 struct SubscriptionData {
-    sending_channel: UnboundedSender<Message>,
+    sending_channel: SubscriptionSender,
     subscription_id: u32,
+    /// The subscription as it's sent to the server.
+    identifier: String,
 }
+/// Ids of subscriptions, unique across the connections of all clients.
+static NEXT_SUBSCRIPTION_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Senders waiting for the responses to posts, by request id.
+type PendingPosts = Arc<Mutex<HashMap<u64, oneshot::Sender<PostResponse>>>>;
+type WsWriter = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, protocol::Message>;
+type WsReader = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+
+/// The server closes connections it hasn't sent anything on for a minute,
+/// so connections of quiet subscriptions are kept open by pinging.
+const PING_INTERVAL: Duration = Duration::from_secs(50);
+/// Pause before reconnecting, doubled after every failed attempt up to
+/// `MAX_RECONNECT_DELAY`.
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Channels parsed into a `Message` of their own. Messages of other channels
+/// are kept as `Message::Unknown`.
+const CHANNELS: [&str; 19] = [
+    "allMids",
+    "trades",
+    "l2Book",
+    "user",
+    "userFills",
+    "candle",
+    "subscriptionResponse",
+    "orderUpdates",
+    "activeAssetCtx",
+    "activeSpotAssetCtx",
+    "webData2",
+    "notification",
+    "userFundings",
+    "userNonFundingLedgerUpdates",
+    "activeAssetData",
+    "bbo",
+    "post",
+    "pong",
+    "error",
+];
+
 pub(crate) struct WsManager {
-    writer: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, protocol::Message>,
+    /// Replaced by the reader when it reconnects.
+    writer: Arc<Mutex<WsWriter>>,
+    /// Unset while the reader reconnects.
+    connected: Arc<AtomicBool>,
     subscriptions: Arc<Mutex<HashMap<String, Vec<SubscriptionData>>>>,
-    subscription_id: u32,
+    pending_posts: PendingPosts,
+    next_post_id: AtomicU64,
     subscription_identifiers: HashMap<u32, String>,
+    reader_handle: JoinHandle<()>,
+    heartbeat_handle: JoinHandle<()>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "type")]
 #[serde(rename_all = "camelCase")]
 pub enum Subscription {
     AllMids,
-    Trades { coin: String },
-    L2Book { coin: String },
-    UserEvents { user: H160 },
-    UserFills { user: H160 },
-    Candle { coin: String, interval: String },
-    OrderUpdates { user: H160 },
+    Trades {
+        coin: String,
+    },
+    /// Book of `coin`, with prices aggregated to `n_sig_figs` significant
+    /// figures, from 2 to 5, and, with 5 significant figures, the last one
+    /// rounded to a multiple of `mantissa`, which is 2 or 5. Messages don't
+    /// name the aggregation, so each aggregation other than the default is
+    /// subscribed to on a connection of its own.
+    #[serde(rename_all = "camelCase")]
+    L2Book {
+        coin: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        n_sig_figs: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mantissa: Option<u32>,
+    },
+    UserEvents {
+        user: H160,
+    },
+    UserFills {
+        user: H160,
+    },
+    Candle {
+        coin: String,
+        interval: String,
+    },
+    OrderUpdates {
+        user: H160,
+    },
+    // Spot pairs are sent as `Message::ActiveSpotAssetCtx`
+    ActiveAssetCtx {
+        coin: String,
+    },
+    WebData2 {
+        user: H160,
+    },
+    Notification {
+        user: H160,
+    },
+    UserFundings {
+        user: H160,
+    },
+    UserNonFundingLedgerUpdates {
+        user: H160,
+    },
+    ActiveAssetData {
+        user: H160,
+        coin: String,
+    },
+    Bbo {
+        coin: String,
+    },
+}
+
+/// Connection of its own that a subscription needs, since its messages
+/// can't be told apart from those of similar subscriptions.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum ConnectionKey {
+    /// User events, order updates and notifications don't name their user.
+    User(H160),
+    /// Books don't name their aggregation.
+    L2Book {
+        coin: String,
+        n_sig_figs: Option<u32>,
+        mantissa: Option<u32>,
+    },
+}
+
+impl Subscription {
+    /// The connection of its own that the subscription needs, if any.
+    pub(crate) fn connection_key(&self) -> Option<ConnectionKey> {
+        match self {
+            Self::UserEvents { user }
+            | Self::OrderUpdates { user }
+            | Self::Notification { user } => Some(ConnectionKey::User(*user)),
+            Self::L2Book {
+                coin,
+                n_sig_figs,
+                mantissa,
+            } if n_sig_figs.is_some() || mantissa.is_some() => Some(ConnectionKey::L2Book {
+                coin: coin.clone(),
+                n_sig_figs: *n_sig_figs,
+                mantissa: *mantissa,
+            }),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "channel")]
 #[serde(rename_all = "camelCase")]
 pub enum Message {
@@ -58,6 +203,22 @@ pub enum Message {
     Candle(Candle),
     SubscriptionResponse,
     OrderUpdates(OrderUpdates),
+    ActiveAssetCtx(ActiveAssetCtx),
+    ActiveSpotAssetCtx(ActiveSpotAssetCtx),
+    WebData2(WebData2),
+    Notification(Notification),
+    UserFundings(UserFundings),
+    UserNonFundingLedgerUpdates(UserNonFundingLedgerUpdates),
+    ActiveAssetData(ActiveAssetData),
+    Bbo(Bbo),
+    Post(Post),
+    Pong,
+    Error(ErrorMessage),
+    /// A message of a channel that isn't known yet, as it was received.
+    /// Messages of known channels that don't match their type fail to parse
+    /// instead.
+    #[serde(skip)]
+    Unknown(Value),
 }
 
 #[derive(Serialize)]
@@ -66,34 +227,232 @@ pub(crate) struct SubscriptionSendData<'a> {
     subscription: &'a serde_json::Value,
 }
 
+/// Request sent with the websocket `post` method instead of an HTTP request
+/// to `/info` or `/exchange`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", content = "payload", rename_all = "camelCase")]
+pub(crate) enum PostRequest {
+    Action(serde_json::Value),
+}
+
+#[derive(Serialize)]
+struct PingSendData {
+    method: &'static str,
+}
+
+#[derive(Serialize)]
+struct PostSendData<'a> {
+    method: &'static str,
+    id: u64,
+    request: &'a PostRequest,
+}
+
 impl WsManager {
     pub(crate) async fn new(url: String) -> Result<WsManager> {
-        let (ws_stream, _) = connect_async(url.clone())
-            .await
-            .map_err(|e| Error::Websocket(e.to_string()))?;
+        WsManager::with_ping_interval(url, PING_INTERVAL).await
+    }
 
-        let (writer, mut reader) = ws_stream.split();
+    /// Connects to `url`, reconnecting and renewing the subscriptions
+    /// whenever the connection is lost.
+    async fn with_ping_interval(url: String, ping_interval: Duration) -> Result<WsManager> {
+        let (writer, mut reader) = WsManager::connect(&url).await?;
+        let writer = Arc::new(Mutex::new(writer));
+        let writer_copy = Arc::clone(&writer);
+        let connected = Arc::new(AtomicBool::new(true));
+        let connected_copy = Arc::clone(&connected);
 
         let subscriptions_map: HashMap<String, Vec<SubscriptionData>> = HashMap::new();
         let subscriptions = Arc::new(Mutex::new(subscriptions_map));
         let subscriptions_copy = Arc::clone(&subscriptions);
+        let pending_posts: PendingPosts = Arc::new(Mutex::new(HashMap::new()));
+        let pending_posts_copy = Arc::clone(&pending_posts);
 
+        let span = info_span!("websocket", %url);
         let reader_fut = async move {
-            // TODO: reconnect
             loop {
-                let data = reader.next().await;
-                if let Err(err) = WsManager::parse_and_send_data(data, &subscriptions_copy).await {
-                    error!("Error processing data received by WS manager reader: {err}");
+                match reader.next().await {
+                    Some(Ok(data)) => {
+                        if let Err(err) = WsManager::parse_and_send_data(
+                            data,
+                            &subscriptions_copy,
+                            &pending_posts_copy,
+                        )
+                        .await
+                        {
+                            error!("Error processing data received by WS manager reader: {err}");
+                        }
+                        continue;
+                    }
+                    Some(Err(err)) => error!("WS manager reader failed: {err}"),
+                    None => error!("WS manager reader stream ended"),
                 }
+                connected_copy.store(false, Ordering::Release);
+                record_ws_disconnect();
+                // Fail the posts still waiting for a response
+                pending_posts_copy.lock().await.clear();
+                reader = WsManager::reconnect(&url, &writer_copy, &subscriptions_copy).await;
+                connected_copy.store(true, Ordering::Release);
             }
         };
-        spawn(reader_fut);
+        let reader_handle = spawn(reader_fut.instrument(span.clone()));
+        let heartbeat_handle =
+            spawn(WsManager::heartbeat(Arc::clone(&writer), ping_interval).instrument(span));
 
         Ok(WsManager {
             writer,
+            connected,
             subscriptions,
-            subscription_id: 0,
+            pending_posts,
+            next_post_id: AtomicU64::new(0),
             subscription_identifiers: HashMap::new(),
+            reader_handle,
+            heartbeat_handle,
+        })
+    }
+
+    async fn connect(url: &str) -> Result<(WsWriter, WsReader)> {
+        let (ws_stream, _) = connect_async(url)
+            .await
+            .map_err(|e| Error::Websocket(e.to_string()))?;
+        record_ws_connect();
+        Ok(ws_stream.split())
+    }
+
+    /// Connects to `url` again, retrying until it succeeds, and subscribes
+    /// to all current subscriptions on the new connection.
+    async fn reconnect(
+        url: &str,
+        writer: &Mutex<WsWriter>,
+        subscriptions: &Mutex<HashMap<String, Vec<SubscriptionData>>>,
+    ) -> WsReader {
+        let mut delay = RECONNECT_DELAY;
+        loop {
+            sleep(delay).await;
+            let (new_writer, reader) = match WsManager::connect(url).await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    warn!("Error reconnecting, retrying in {delay:?}: {err}");
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    continue;
+                }
+            };
+
+            // Locked until resubscribed, so that subscriptions added in the
+            // meantime are only subscribed to once
+            let subscriptions = subscriptions.lock().await;
+            let mut writer = writer.lock().await;
+            *writer = new_writer;
+            record_ws_reconnect();
+            info!(
+                "Reconnected, renewing {} subscriptions",
+                subscriptions.len()
+            );
+            for subscription_data in subscriptions.values().filter_map(|datas| datas.first()) {
+                // A failed send means the new connection was lost as well,
+                // which the reader notices
+                if let Err(err) = WsManager::send_subscription(
+                    &mut writer,
+                    "subscribe",
+                    &subscription_data.identifier,
+                )
+                .await
+                {
+                    error!("Error renewing {}: {err}", subscription_data.identifier);
+                }
+            }
+            return reader;
+        }
+    }
+
+    /// Pings the server every `ping_interval`.
+    async fn heartbeat(writer: Arc<Mutex<WsWriter>>, ping_interval: Duration) {
+        let Ok(ping) = serde_json::to_string(&PingSendData { method: "ping" }) else {
+            return;
+        };
+        loop {
+            sleep(ping_interval).await;
+            if let Err(err) = writer
+                .lock()
+                .await
+                .send(protocol::Message::Text(ping.clone()))
+                .await
+            {
+                // The reader reconnects once it notices the connection is lost
+                debug!("Error sending ping: {err}");
+            }
+        }
+    }
+
+    async fn send_subscription(
+        writer: &mut WsWriter,
+        method: &'static str,
+        identifier: &str,
+    ) -> Result<()> {
+        let payload = serde_json::to_string(&SubscriptionSendData {
+            method,
+            subscription: &serde_json::from_str::<serde_json::Value>(identifier)
+                .map_err(|e| Error::JsonParse(e.to_string()))?,
+        })
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        writer
+            .send(protocol::Message::Text(payload))
+            .await
+            .map_err(|e| Error::Websocket(e.to_string()))
+    }
+
+    /// Name of the channel `message` was received on.
+    pub(crate) fn channel(message: &Message) -> &'static str {
+        match message {
+            Message::AllMids(_) => "allMids",
+            Message::Trades(_) => "trades",
+            Message::L2Book(_) => "l2Book",
+            Message::User(_) => "user",
+            Message::UserFills(_) => "userFills",
+            Message::Candle(_) => "candle",
+            Message::SubscriptionResponse => "subscriptionResponse",
+            Message::OrderUpdates(_) => "orderUpdates",
+            Message::ActiveAssetCtx(_) => "activeAssetCtx",
+            Message::ActiveSpotAssetCtx(_) => "activeSpotAssetCtx",
+            Message::WebData2(_) => "webData2",
+            Message::Notification(_) => "notification",
+            Message::UserFundings(_) => "userFundings",
+            Message::UserNonFundingLedgerUpdates(_) => "userNonFundingLedgerUpdates",
+            Message::ActiveAssetData(_) => "activeAssetData",
+            Message::Bbo(_) => "bbo",
+            Message::Post(_) => "post",
+            Message::Pong => "pong",
+            Message::Error(_) => "error",
+            Message::Unknown(_) => "unknown",
+        }
+    }
+
+    /// Key of the snapshot messages that are superseded by the next message
+    /// with the same key, like mids and books.
+    pub(crate) fn conflation_key(message: &Message) -> Option<String> {
+        match message {
+            Message::AllMids(_)
+            | Message::L2Book(_)
+            | Message::Bbo(_)
+            | Message::ActiveAssetCtx(_)
+            | Message::ActiveSpotAssetCtx(_)
+            | Message::ActiveAssetData(_)
+            | Message::WebData2(_) => WsManager::get_identifier(message)
+                .ok()
+                .map(|identifier| format!("{}:{identifier}", WsManager::channel(message))),
+            _ => None,
+        }
+    }
+
+    /// Parses a message received as `data`, keeping messages of unknown
+    /// channels as `Message::Unknown`.
+    fn parse_message(data: &str) -> Result<Message> {
+        serde_json::from_str::<Message>(data).or_else(|err| {
+            let value =
+                serde_json::from_str::<Value>(data).map_err(|e| Error::JsonParse(e.to_string()))?;
+            match value.get("channel").and_then(Value::as_str) {
+                Some(channel) if !CHANNELS.contains(&channel) => Ok(Message::Unknown(value)),
+                _ => Err(Error::JsonParse(err.to_string())),
+            }
         })
     }
 
@@ -102,7 +461,10 @@ impl WsManager {
             Message::AllMids(_) => serde_json::to_string(&Subscription::AllMids)
                 .map_err(|e| Error::JsonParse(e.to_string())),
             Message::User(_) => Ok("userEvents".to_string()),
-            Message::UserFills(_) => Ok("userFills".to_string()),
+            Message::UserFills(user_fills) => serde_json::to_string(&Subscription::UserFills {
+                user: user_fills.data.user,
+            })
+            .map_err(|e| Error::JsonParse(e.to_string())),
             Message::Trades(trades) => {
                 if trades.data.is_empty() {
                     Ok(String::default())
@@ -115,6 +477,8 @@ impl WsManager {
             }
             Message::L2Book(l2_book) => serde_json::to_string(&Subscription::L2Book {
                 coin: l2_book.data.coin.clone(),
+                n_sig_figs: None,
+                mantissa: None,
             })
             .map_err(|e| Error::JsonParse(e.to_string())),
             Message::Candle(candle) => serde_json::to_string(&Subscription::Candle {
@@ -123,99 +487,221 @@ impl WsManager {
             })
             .map_err(|e| Error::JsonParse(e.to_string())),
             Message::OrderUpdates(_) => Ok("orderUpdates".to_string()),
-            Message::SubscriptionResponse => Ok(String::default()),
+            Message::ActiveAssetCtx(active_asset_ctx) => {
+                serde_json::to_string(&Subscription::ActiveAssetCtx {
+                    coin: active_asset_ctx.data.coin.clone(),
+                })
+                .map_err(|e| Error::JsonParse(e.to_string()))
+            }
+            Message::ActiveSpotAssetCtx(active_spot_asset_ctx) => {
+                serde_json::to_string(&Subscription::ActiveAssetCtx {
+                    coin: active_spot_asset_ctx.data.coin.clone(),
+                })
+                .map_err(|e| Error::JsonParse(e.to_string()))
+            }
+            Message::WebData2(web_data) => serde_json::to_string(&Subscription::WebData2 {
+                user: web_data.data.user,
+            })
+            .map_err(|e| Error::JsonParse(e.to_string())),
+            Message::Notification(_) => Ok("notification".to_string()),
+            Message::UserFundings(user_fundings) => {
+                serde_json::to_string(&Subscription::UserFundings {
+                    user: user_fundings.data.user,
+                })
+                .map_err(|e| Error::JsonParse(e.to_string()))
+            }
+            Message::UserNonFundingLedgerUpdates(ledger_updates) => {
+                serde_json::to_string(&Subscription::UserNonFundingLedgerUpdates {
+                    user: ledger_updates.data.user,
+                })
+                .map_err(|e| Error::JsonParse(e.to_string()))
+            }
+            Message::ActiveAssetData(active_asset_data) => {
+                serde_json::to_string(&Subscription::ActiveAssetData {
+                    user: active_asset_data.data.user,
+                    coin: active_asset_data.data.coin.clone(),
+                })
+                .map_err(|e| Error::JsonParse(e.to_string()))
+            }
+            Message::Bbo(bbo) => serde_json::to_string(&Subscription::Bbo {
+                coin: bbo.data.coin.clone(),
+            })
+            .map_err(|e| Error::JsonParse(e.to_string())),
+            Message::SubscriptionResponse
+            | Message::Post(_)
+            | Message::Pong
+            | Message::Error(_)
+            | Message::Unknown(_) => Ok(String::default()),
         }
     }
 
+    /// Key the messages of the subscription with `identifier` are routed by.
+    /// Subscriptions with a `Subscription::connection_key` get a connection
+    /// of their own, since their messages are routed by type, as they don't
+    /// name their user, or by coin, as they don't name their aggregation.
+    fn identifier_entry(identifier: &str) -> Result<String> {
+        let subscription = serde_json::from_str::<Subscription>(identifier)
+            .map_err(|e| Error::JsonParse(e.to_string()))?;
+        Ok(match subscription {
+            Subscription::UserEvents { .. } => "userEvents".to_string(),
+            Subscription::OrderUpdates { .. } => "orderUpdates".to_string(),
+            Subscription::Notification { .. } => "notification".to_string(),
+            // Updates are routed by coin, whatever their aggregation
+            Subscription::L2Book { coin, .. } => serde_json::to_string(&Subscription::L2Book {
+                coin,
+                n_sig_figs: None,
+                mantissa: None,
+            })
+            .map_err(|e| Error::JsonParse(e.to_string()))?,
+            _ => identifier.to_string(),
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
     async fn parse_and_send_data(
-        data: Option<std::result::Result<protocol::Message, tungstenite::Error>>,
+        data: protocol::Message,
         subscriptions: &Arc<Mutex<HashMap<String, Vec<SubscriptionData>>>>,
+        pending_posts: &PendingPosts,
     ) -> Result<()> {
+        let received = Instant::now();
         let data = data
-            .ok_or(Error::ReaderDataNotFound)?
-            .map_err(|e| Error::GenericReader(e.to_string()))?
             .into_text()
             .map_err(|e| Error::ReaderTextConversion(e.to_string()))?;
         if !data.starts_with('{') {
             return Ok(());
         }
-        let message =
-            serde_json::from_str::<Message>(&data).map_err(|e| Error::JsonParse(e.to_string()))?;
+        let message = WsManager::parse_message(&data)?;
+        let channel = WsManager::channel(&message);
+        record_ws_message(channel);
+        if let Message::Post(post) = message {
+            if let Some(sender) = pending_posts.lock().await.remove(&post.data.id) {
+                // The poster may have timed out in the meantime
+                let _ = sender.send(post.data.response);
+            }
+            return Ok(());
+        }
+        match &message {
+            Message::Error(error) => {
+                error!("Websocket error: {}", error.data);
+                return Ok(());
+            }
+            Message::Unknown(_) => {
+                debug!("Ignoring message of an unknown channel: {data}");
+                return Ok(());
+            }
+            _ => {}
+        }
         let identifier = WsManager::get_identifier(&message)?;
         if identifier.is_empty() {
             return Ok(());
         }
 
-        let mut subscriptions = subscriptions.lock().await;
+        // Sent to without holding the lock, since a channel that blocks
+        // when full may wait for a consumer that is adding or removing a
+        // subscription
+        let Some(sending_channels) = subscriptions.lock().await.get(&identifier).map(|datas| {
+            datas
+                .iter()
+                .map(|subscription_data| subscription_data.sending_channel.clone())
+                .collect::<Vec<_>>()
+        }) else {
+            return Ok(());
+        };
         let mut res = Ok(());
-        if let Some(subscription_datas) = subscriptions.get_mut(&identifier) {
-            for subscription_data in subscription_datas {
-                if let Err(e) = subscription_data
-                    .sending_channel
-                    .send(message.clone())
-                    .map_err(|e| Error::WsSend(e.to_string()))
-                {
-                    res = Err(e);
-                }
+        for sending_channel in sending_channels {
+            if let Err(e) = sending_channel.send(message.clone()).await {
+                res = Err(e);
             }
         }
+        record_ws_dispatch(channel, received);
         res
     }
 
+    /// Sends `request` with the `post` method and waits up to `timeout` for
+    /// the response with its id.
+    ///
+    /// Returns `Error::Websocket` if the request couldn't be sent, and
+    /// `Error::PostUnconfirmed` if it was sent but no response arrived.
+    pub(crate) async fn post(
+        &self,
+        request: PostRequest,
+        timeout_duration: Duration,
+    ) -> Result<PostResponse> {
+        if !self.connected.load(Ordering::Acquire) {
+            return Err(Error::Websocket("Reconnecting".to_string()));
+        }
+        let id = self.next_post_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        self.pending_posts.lock().await.insert(id, sender);
+
+        let payload = serde_json::to_string(&PostSendData {
+            method: "post",
+            id,
+            request: &request,
+        })
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        if let Err(e) = self
+            .writer
+            .lock()
+            .await
+            .send(protocol::Message::Text(payload))
+            .await
+        {
+            self.pending_posts.lock().await.remove(&id);
+            return Err(Error::Websocket(e.to_string()));
+        }
+
+        match timeout(timeout_duration, receiver).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(Error::PostUnconfirmed(format!(
+                "Connection closed before the response to post {id}"
+            ))),
+            Err(_) => {
+                self.pending_posts.lock().await.remove(&id);
+                Err(Error::PostUnconfirmed(format!(
+                    "No response to post {id} within {timeout_duration:?}"
+                )))
+            }
+        }
+    }
+
     pub(crate) async fn add_subscription(
         &mut self,
         identifier: String,
-        sending_channel: UnboundedSender<Message>,
+        sending_channel: SubscriptionSender,
     ) -> Result<u32> {
         let mut subscriptions = self.subscriptions.lock().await;
 
-        let identifier_entry = if let Subscription::UserEvents { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
-        {
-            "userEvents".to_string()
-        } else if let Subscription::OrderUpdates { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
-        {
-            "orderUpdates".to_string()
-        } else {
-            identifier.clone()
-        };
+        let identifier_entry = WsManager::identifier_entry(&identifier)?;
         let subscriptions = subscriptions
             .entry(identifier_entry.clone())
             .or_insert(Vec::new());
 
-        if !subscriptions.is_empty() && identifier_entry.eq("userEvents") {
-            return Err(Error::UserEvents);
-        }
-
         if subscriptions.is_empty() {
-            let payload = serde_json::to_string(&SubscriptionSendData {
-                method: "subscribe",
-                subscription: &serde_json::from_str::<serde_json::Value>(&identifier)
-                    .map_err(|e| Error::JsonParse(e.to_string()))?,
-            })
-            .map_err(|e| Error::JsonParse(e.to_string()))?;
-
-            self.writer
-                .send(protocol::Message::Text(payload))
-                .await
-                .map_err(|e| Error::Websocket(e.to_string()))?;
+            WsManager::send_subscription(&mut *self.writer.lock().await, "subscribe", &identifier)
+                .await?;
         }
 
-        let subscription_id = self.subscription_id;
+        let subscription_id = NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed);
         self.subscription_identifiers
             .insert(subscription_id, identifier.clone());
         subscriptions.push(SubscriptionData {
             sending_channel,
             subscription_id,
+            identifier,
         });
 
-        self.subscription_id += 1;
         Ok(subscription_id)
     }
 
+    pub(crate) fn has_subscription(&self, subscription_id: u32) -> bool {
+        self.subscription_identifiers.contains_key(&subscription_id)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.subscription_identifiers.is_empty()
+    }
+
     pub(crate) async fn remove_subscription(&mut self, subscription_id: u32) -> Result<()> {
         let identifier = self
             .subscription_identifiers
@@ -223,19 +709,7 @@ impl WsManager {
             .ok_or(Error::SubscriptionNotFound)?
             .clone();
 
-        let identifier_entry = if let Subscription::UserEvents { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
-        {
-            "userEvents".to_string()
-        } else if let Subscription::OrderUpdates { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
-        {
-            "orderUpdates".to_string()
-        } else {
-            identifier.clone()
-        };
+        let identifier_entry = WsManager::identifier_entry(&identifier)?;
 
         self.subscription_identifiers.remove(&subscription_id);
 
@@ -251,17 +725,473 @@ impl WsManager {
         subscriptions.remove(index);
 
         if subscriptions.is_empty() {
-            let payload = serde_json::to_string(&SubscriptionSendData {
-                method: "unsubscribe",
-                subscription: &serde_json::from_str::<serde_json::Value>(&identifier)
-                    .map_err(|e| Error::JsonParse(e.to_string()))?,
+            WsManager::send_subscription(
+                &mut *self.writer.lock().await,
+                "unsubscribe",
+                &identifier,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WsManager {
+    fn drop(&mut self) {
+        // Stop forwarding messages once nobody can manage the subscriptions anymore
+        self.reader_handle.abort();
+        self.heartbeat_handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bounded_channel, OverflowPolicy};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    #[test]
+    fn test_asset_ctx_updates_are_routed_to_their_subscription() -> Result<()> {
+        let subscription = serde_json::to_string(&Subscription::ActiveAssetCtx {
+            coin: "@107".to_string(),
+        })
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        assert_eq!(subscription, r#"{"type":"activeAssetCtx","coin":"@107"}"#);
+
+        let spot: Message = serde_json::from_str(
+            r#"{"channel":"activeSpotAssetCtx","data":{"coin":"@107","ctx":{
+                "dayNtlVlm":"8906.0","prevDayPx":"0.2","markPx":"0.21","midPx":"0.205",
+                "circulatingSupply":"851681534.05","coin":"@107"}}}"#,
+        )
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        assert_eq!(WsManager::channel(&spot), "activeSpotAssetCtx");
+        assert_eq!(WsManager::get_identifier(&spot)?, subscription);
+
+        let perp: Message = serde_json::from_str(
+            r#"{"channel":"activeAssetCtx","data":{"coin":"ETH","ctx":{
+                "dayNtlVlm":"1169046.29","prevDayPx":"3300.1","markPx":"3310.5","midPx":"3310.45",
+                "funding":"0.0000125","openInterest":"688.11","oraclePx":"3311.0"}}}"#,
+        )
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let Message::ActiveAssetCtx(active_asset_ctx) = &perp else {
+            panic!("unexpected message {perp:?}");
+        };
+        assert_eq!(active_asset_ctx.data.ctx.oracle_px, "3311.0");
+        assert!(active_asset_ctx.data.ctx.premium.is_none());
+        assert_eq!(
+            WsManager::get_identifier(&perp)?,
+            r#"{"type":"activeAssetCtx","coin":"ETH"}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_and_bbo_channels_are_typed() -> Result<()> {
+        let user = "0xc64cc00b46101bd40aa1c3121195e85c0b0918d8";
+        let messages = [
+            (
+                r#"{"channel":"webData2","data":{"user":"0xc64cc00b46101bd40aa1c3121195e85c0b0918d8",
+                "serverTime":1724361546645,"openOrders":[]}}"#,
+                "webData2",
+                format!(r#"{{"type":"webData2","user":"{user}"}}"#),
+            ),
+            (
+                r#"{"channel":"notification","data":{"notification":"Order filled"}}"#,
+                "notification",
+                "notification".to_string(),
+            ),
+            (
+                r#"{"channel":"userFundings","data":{"isSnapshot":true,
+                "user":"0xc64cc00b46101bd40aa1c3121195e85c0b0918d8","fundings":[{"time":1681222254710,
+                "coin":"ETH","usdc":"-3.625312","szi":"49.1477","fundingRate":"0.0000417"}]}}"#,
+                "userFundings",
+                format!(r#"{{"type":"userFundings","user":"{user}"}}"#),
+            ),
+            (
+                r#"{"channel":"userNonFundingLedgerUpdates","data":{
+                "user":"0xc64cc00b46101bd40aa1c3121195e85c0b0918d8","nonFundingLedgerUpdates":[
+                {"time":1681222254710,"hash":"0x01","delta":{"type":"deposit","usdc":"100.0"}}]}}"#,
+                "userNonFundingLedgerUpdates",
+                format!(r#"{{"type":"userNonFundingLedgerUpdates","user":"{user}"}}"#),
+            ),
+            (
+                r#"{"channel":"activeAssetData","data":{
+                "user":"0xc64cc00b46101bd40aa1c3121195e85c0b0918d8","coin":"ETH",
+                "leverage":{"type":"cross","value":3},"maxTradeSzs":["1.5","1.4"],
+                "availableToTrade":["5000.0","4900.0"]}}"#,
+                "activeAssetData",
+                format!(r#"{{"type":"activeAssetData","user":"{user}","coin":"ETH"}}"#),
+            ),
+            (
+                r#"{"channel":"bbo","data":{"coin":"ETH","time":1724361546645,
+                "bbo":[{"px":"3310.4","sz":"1.2","n":3},null]}}"#,
+                "bbo",
+                r#"{"type":"bbo","coin":"ETH"}"#.to_string(),
+            ),
+        ];
+        for (data, channel, identifier) in messages {
+            let message: Message =
+                serde_json::from_str(data).map_err(|e| Error::JsonParse(e.to_string()))?;
+            assert_eq!(WsManager::channel(&message), channel);
+            assert!(CHANNELS.contains(&channel));
+            assert_eq!(WsManager::get_identifier(&message)?, identifier);
+        }
+
+        let pong: Message = serde_json::from_str(r#"{"channel":"pong"}"#)
+            .map_err(|e| Error::JsonParse(e.to_string()))?;
+        assert!(matches!(pong, Message::Pong));
+        let unknown = WsManager::parse_message(
+            r#"{"channel":"someNewChannel","data":{"coin":"ETH","levels":[1,2]}}"#,
+        )?;
+        let Message::Unknown(value) = &unknown else {
+            panic!("expected an unknown message, got {unknown:?}");
+        };
+        assert_eq!(value["channel"], "someNewChannel");
+        assert_eq!(
+            value["data"],
+            serde_json::json!({"coin": "ETH", "levels": [1, 2]})
+        );
+        assert!(WsManager::get_identifier(&unknown)?.is_empty());
+
+        // A known channel that changed its schema isn't mistaken for an
+        // unknown one
+        assert!(matches!(
+            WsManager::parse_message(r#"{"channel":"user","data":{"fills":[{"coin":"ETH"}]}}"#),
+            Err(Error::JsonParse(_))
+        ));
+        let error: Message = serde_json::from_str(
+            r#"{"channel":"error","data":"Invalid subscription {\"type\":\"l2Book\"}"}"#,
+        )
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        assert!(matches!(error, Message::Error(_)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_posts_are_matched_with_their_responses() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| Error::Io(e.to_string()))?;
+        let address = listener
+            .local_addr()
+            .map_err(|e| Error::Io(e.to_string()))?;
+        // Answers the first two posts in reverse order and ignores the third
+        spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept_async(stream).await.unwrap();
+            let mut ids = Vec::new();
+            while ids.len() < 2 {
+                let message = ws_stream.next().await.unwrap().unwrap();
+                let request: serde_json::Value =
+                    serde_json::from_str(message.to_text().unwrap()).unwrap();
+                assert_eq!(request["method"], "post");
+                assert_eq!(request["request"]["type"], "action");
+                ids.push(request["id"].as_u64().unwrap());
+            }
+            for id in ids.into_iter().rev() {
+                let response = serde_json::json!({
+                    "channel": "post",
+                    "data": {
+                        "id": id,
+                        "response": {"type": "action", "payload": {"status": "ok", "id": id}},
+                    },
+                });
+                ws_stream
+                    .send(protocol::Message::Text(response.to_string()))
+                    .await
+                    .unwrap();
+            }
+            while ws_stream.next().await.is_some() {}
+        });
+
+        let ws_manager = WsManager::new(format!("ws://{address}")).await?;
+        let timeout = Duration::from_secs(5);
+        let (first, second) = tokio::join!(
+            ws_manager.post(PostRequest::Action(serde_json::json!({"n": 0})), timeout),
+            ws_manager.post(PostRequest::Action(serde_json::json!({"n": 1})), timeout),
+        );
+        for (id, response) in [(0, first?), (1, second?)] {
+            let PostResponse::Action(payload) = response else {
+                panic!("unexpected response {response:?}");
+            };
+            assert_eq!(payload["id"], id);
+        }
+
+        let unanswered = ws_manager
+            .post(
+                PostRequest::Action(serde_json::json!({"n": 2})),
+                Duration::from_millis(50),
+            )
+            .await;
+        assert!(matches!(unanswered, Err(Error::PostUnconfirmed(_))));
+        assert!(ws_manager.pending_posts.lock().await.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_l2_book_aggregation_is_subscribed_once_per_connection() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| Error::Io(e.to_string()))?;
+        let address = listener
+            .local_addr()
+            .map_err(|e| Error::Io(e.to_string()))?;
+        let (requests_sender, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept_async(stream).await.unwrap();
+            while let Some(Ok(message)) = ws_stream.next().await {
+                let _ = requests_sender.send(message.to_text().unwrap().to_string());
+            }
+        });
+
+        let mut ws_manager = WsManager::new(format!("ws://{address}")).await?;
+        let aggregated = |n_sig_figs| {
+            serde_json::to_string(&Subscription::L2Book {
+                coin: "kPEPE".to_string(),
+                n_sig_figs: Some(n_sig_figs),
+                mantissa: None,
             })
+            .map_err(|e| Error::JsonParse(e.to_string()))
+        };
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        ws_manager
+            .add_subscription(aggregated(3)?, sender.clone().into())
+            .await?;
+        ws_manager
+            .add_subscription(aggregated(3)?, sender.into())
+            .await?;
+
+        let request: serde_json::Value =
+            serde_json::from_str(&requests.recv().await.unwrap()).unwrap();
+        assert_eq!(
+            request,
+            serde_json::json!({
+                "method": "subscribe",
+                "subscription": {"type": "l2Book", "coin": "kPEPE", "nSigFigs": 3},
+            })
+        );
+        let update: Message = serde_json::from_str(
+            r#"{"channel":"l2Book","data":{"coin":"kPEPE","time":1,"levels":[[],[]]}}"#,
+        )
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        assert!(ws_manager
+            .subscriptions
+            .lock()
+            .await
+            .get(&WsManager::get_identifier(&update)?)
+            .is_some_and(|subscription_datas| subscription_datas.len() == 2));
+
+        // Other aggregations of the book get connections of their own
+        let connection_key = |n_sig_figs| {
+            Subscription::L2Book {
+                coin: "kPEPE".to_string(),
+                n_sig_figs,
+                mantissa: None,
+            }
+            .connection_key()
+        };
+        assert_eq!(connection_key(Some(3)), connection_key(Some(3)));
+        assert_ne!(connection_key(Some(3)), connection_key(Some(4)));
+        assert_eq!(connection_key(None), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_user_channels_are_routed_by_user() -> Result<()> {
+        let users = [H160::from_low_u64_be(1), H160::from_low_u64_be(2)];
+        let mut subscriptions = HashMap::new();
+        let mut receivers = Vec::new();
+        for (subscription_id, user) in (0..).zip(users) {
+            let (sending_channel, receiver) = tokio::sync::mpsc::unbounded_channel();
+            let identifier = serde_json::to_string(&Subscription::UserFills { user })
+                .map_err(|e| Error::JsonParse(e.to_string()))?;
+            subscriptions.insert(
+                WsManager::identifier_entry(&identifier)?,
+                vec![SubscriptionData {
+                    sending_channel: sending_channel.into(),
+                    subscription_id,
+                    identifier,
+                }],
+            );
+            receivers.push(receiver);
+        }
+        let subscriptions = Arc::new(Mutex::new(subscriptions));
+        let pending_posts: PendingPosts = Arc::new(Mutex::new(HashMap::new()));
+
+        let fills = format!(
+            r#"{{"channel":"userFills","data":{{"user":"{:?}","fills":[]}}}}"#,
+            users[1]
+        );
+        WsManager::parse_and_send_data(
+            protocol::Message::Text(fills),
+            &subscriptions,
+            &pending_posts,
+        )
+        .await?;
+
+        assert!(receivers[0].try_recv().is_err());
+        let Ok(Message::UserFills(user_fills)) = receivers[1].try_recv() else {
+            panic!("fills weren't routed to their user");
+        };
+        assert_eq!(user_fills.data.user, users[1]);
+
+        let user_events = Subscription::UserEvents { user: users[0] };
+        assert_eq!(
+            user_events.connection_key(),
+            Some(ConnectionKey::User(users[0]))
+        );
+        assert_eq!(
+            Subscription::UserFills { user: users[0] }.connection_key(),
+            None
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_idle_connection_is_kept_open() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| Error::Io(e.to_string()))?;
+        let address = listener
+            .local_addr()
+            .map_err(|e| Error::Io(e.to_string()))?;
+        // Accepts a single connection and, like the exchange, closes it once
+        // nothing was received for a while. Sends user events after a few
+        // pings, when the connection would have been closed without them.
+        spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept_async(stream).await.unwrap();
+            let mut pings = 0;
+            while let Ok(Some(Ok(message))) =
+                timeout(Duration::from_millis(200), ws_stream.next()).await
+            {
+                let request: serde_json::Value =
+                    serde_json::from_str(message.to_text().unwrap()).unwrap();
+                if request["method"] != "ping" {
+                    continue;
+                }
+                pings += 1;
+                let response = if pings == 5 {
+                    r#"{"channel":"user","data":{"fills":[]}}"#
+                } else {
+                    r#"{"channel":"pong"}"#
+                };
+                ws_stream
+                    .send(protocol::Message::Text(response.to_string()))
+                    .await
+                    .unwrap();
+            }
+            ws_stream.close(None).await.unwrap();
+        });
+
+        let mut ws_manager =
+            WsManager::with_ping_interval(format!("ws://{address}"), Duration::from_millis(50))
+                .await?;
+        let identifier = serde_json::to_string(&Subscription::UserEvents {
+            user: H160::from_low_u64_be(1),
+        })
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        ws_manager
+            .add_subscription(identifier, sender.into())
+            .await?;
+
+        let message = timeout(Duration::from_secs(5), receiver.recv()).await;
+        assert!(matches!(message, Ok(Some(Message::User(_)))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_are_renewed_after_reconnecting() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| Error::Io(e.to_string()))?;
+        let address = listener
+            .local_addr()
+            .map_err(|e| Error::Io(e.to_string()))?;
+        // Drops the first connection after the subscription and sends mids
+        // once the subscription is renewed on the second
+        spawn(async move {
+            let mut subscribed = Vec::new();
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws_stream = accept_async(stream).await.unwrap();
+                let message = ws_stream.next().await.unwrap().unwrap();
+                subscribed.push(message.to_text().unwrap().to_string());
+                if subscribed.len() == 2 {
+                    assert_eq!(subscribed[0], subscribed[1]);
+                    ws_stream
+                        .send(protocol::Message::Text(
+                            r#"{"channel":"allMids","data":{"mids":{"ETH":"3310.5"}}}"#.to_string(),
+                        ))
+                        .await
+                        .unwrap();
+                    while ws_stream.next().await.is_some() {}
+                }
+            }
+        });
+
+        let mut ws_manager =
+            WsManager::with_ping_interval(format!("ws://{address}"), Duration::from_secs(60))
+                .await?;
+        let identifier = serde_json::to_string(&Subscription::AllMids)
             .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        ws_manager
+            .add_subscription(identifier, sender.into())
+            .await?;
 
-            self.writer
-                .send(protocol::Message::Text(payload))
-                .await
-                .map_err(|e| Error::Websocket(e.to_string()))?;
+        let message = timeout(Duration::from_secs(5), receiver.recv()).await;
+        assert!(matches!(message, Ok(Some(Message::AllMids(_)))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blocked_channel_does_not_block_subscribing() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| Error::Io(e.to_string()))?;
+        let address = listener
+            .local_addr()
+            .map_err(|e| Error::Io(e.to_string()))?;
+        // Sends two mids, the second of which doesn't fit the channel
+        spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept_async(stream).await.unwrap();
+            ws_stream.next().await.unwrap().unwrap();
+            for mid in ["1", "2"] {
+                let mids =
+                    format!(r#"{{"channel":"allMids","data":{{"mids":{{"ETH":"{mid}"}}}}}}"#);
+                ws_stream.send(protocol::Message::Text(mids)).await.unwrap();
+            }
+            while ws_stream.next().await.is_some() {}
+        });
+
+        let mut ws_manager = WsManager::new(format!("ws://{address}")).await?;
+        let (sender, mut receiver) = bounded_channel(1, OverflowPolicy::Block);
+        let identifier = serde_json::to_string(&Subscription::AllMids)
+            .map_err(|e| Error::JsonParse(e.to_string()))?;
+        ws_manager
+            .add_subscription(identifier, sender.into())
+            .await?;
+        // Give the reader time to block on the second message
+        sleep(Duration::from_millis(100)).await;
+
+        let identifier = serde_json::to_string(&Subscription::Trades {
+            coin: "ETH".to_string(),
+        })
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let subscribed = timeout(
+            Duration::from_secs(5),
+            ws_manager.add_subscription(identifier, sender.into()),
+        )
+        .await;
+        assert!(matches!(subscribed, Ok(Ok(_))));
+        for _ in 0..2 {
+            assert!(matches!(receiver.recv().await, Some(Message::AllMids(_))));
         }
         Ok(())
     }